@@ -1,5 +1,6 @@
 mod node_tunnel_peer;
 mod relay_client;
+mod room_info;
 pub mod protocol;
 mod transport;
 
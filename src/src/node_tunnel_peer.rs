@@ -1,45 +1,98 @@
+use std::collections::HashMap;
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::str::FromStr;
 use std::time::{Duration, Instant};
-use godot::builtin::{Array, Callable, Dictionary, GString, PackedByteArray, Variant};
+use godot::builtin::{Array, Callable, Dictionary, GString, PackedByteArray, PackedInt32Array, Variant, VariantType};
 use godot::prelude::{godot_api, GodotClass};
 use godot::classes::{IMultiplayerPeerExtension, MultiplayerPeerExtension};
 use godot::classes::multiplayer_peer::{ConnectionStatus, TransferMode};
 use godot::global::{godot_error, godot_warn, Error};
 use godot::meta::ToGodot;
-use godot::obj::{Base, WithUserSignals};
-use crate::relay_client::client::RelayClient;
+use godot::obj::{Base, Gd, WithUserSignals};
+use crate::protocol::error_codes;
+use crate::protocol::packet::{GameDataTarget, InviteCode, RoomFilter, RoomSort};
+use crate::relay_client::client::{RelayClient, DEFAULT_MAX_RECONNECT_ATTEMPTS};
 use crate::relay_client::events::RelayEvent;
-use crate::transport::client::ClientTransport;
+use crate::room_info::NodeTunnelRoomInfo;
+use crate::transport::client::{ClientTransport, DEFAULT_CHANNEL_COUNT, DEFAULT_TIMEOUT};
 use crate::transport::common::Channel;
 
+const DEFAULT_JOIN_APPROVAL_TIMEOUT: Duration = Duration::from_secs(30);
+const MIN_INVITE_CODE_LEN: usize = 4;
+const MAX_INVITE_CODE_LEN: usize = 16;
+
 struct GamePacket {
     from_peer: i32,
     data: Vec<u8>,
     transfer_mode: TransferMode,
+    transfer_channel: i32,
+}
+
+struct OutgoingPacket {
+    target: GameDataTarget,
+    data: Vec<u8>,
+    channel: Channel,
+    transfer_channel: u8,
 }
 
 #[derive(GodotClass)]
 #[class(tool, base=MultiplayerPeerExtension)]
 struct NodeTunnelPeer {
     app_id: String,
+    game_version: String,
     unique_id: i32,
     #[var]
     room_id: GString,
     #[var]
+    invite_code: GString,
+    #[var]
     join_validation: Callable,
+    #[var]
+    manual_join_approval: bool,
+    #[var]
+    join_approval_timeout: f64,
+    #[var]
+    relay_timeout: f64,
+    #[var]
+    auto_reconnect: bool,
+    #[var]
+    max_reconnect_attempts: i32,
+    #[var]
+    channel_count: i32,
+    #[var]
+    server_relay: bool,
+    #[var]
+    display_name: GString,
     connection_status: ConnectionStatus,
     target_peer: i32,
     transfer_mode: TransferMode,
+    transfer_channel: i32,
     incoming_packets: Vec<GamePacket>,
     relay_client: RelayClient,
-    outgoing_queue: Vec<(i32, Vec<u8>, Channel)>,
+    outgoing_queue: Vec<OutgoingPacket>,
+    force_kicked_peers: Vec<i32>,
+    connected_peers: Vec<i32>,
+    pending_joins: HashMap<u64, Instant>,
+    refuse_new_connections: bool,
+    peer_metadata: HashMap<i32, String>,
+    room_properties: HashMap<String, String>,
     last_poll_time: Option<Instant>,
     base: Base<MultiplayerPeerExtension>
 }
 
 #[godot_api]
 impl NodeTunnelPeer {
+    #[constant]
+    const ROOM_SORT_DEFAULT: i32 = RoomSort::Default as i32;
+    #[constant]
+    const ROOM_SORT_MOST_PLAYERS: i32 = RoomSort::MostPlayers as i32;
+    #[constant]
+    const ROOM_SORT_FEWEST_PLAYERS: i32 = RoomSort::FewestPlayers as i32;
+    #[constant]
+    const ROOM_SORT_NEWEST: i32 = RoomSort::Newest as i32;
+    #[constant]
+    const ROOM_SORT_OLDEST: i32 = RoomSort::Oldest as i32;
+
     #[signal]
     fn authenticated();
 
@@ -53,11 +106,70 @@ impl NodeTunnelPeer {
     fn forced_disconnect();
 
     #[signal]
-    fn rooms_received(rooms: Array<Variant>);
+    fn room_updated(metadata: String);
+
+    #[signal]
+    fn rooms_received(rooms: Array<Gd<NodeTunnelRoomInfo>>, next_cursor: String);
+
+    #[signal]
+    fn room_full();
+
+    #[signal]
+    fn wrong_password();
+
+    #[signal]
+    fn room_code_taken();
+
+    #[signal]
+    fn room_locked();
+
+    #[signal]
+    fn version_mismatch();
+
+    #[signal]
+    fn join_failed(reason: i64, message: String);
+
+    #[signal]
+    fn join_requested(client_id: i64, metadata: String);
+
+    #[signal]
+    fn room_property_changed(key: String, value: Variant);
+
+    #[signal]
+    fn room_property_conflict(key: String);
+
+    #[signal]
+    fn room_list_changed(room_id: String, room: Variant);
+
+    #[signal]
+    fn kicked(reason: String);
+
+    #[signal]
+    fn disconnected_from_relay(reason: String);
+
+    #[signal]
+    fn reconnecting(attempt: i64);
+
+    #[signal]
+    fn reconnected();
+
+    #[signal]
+    fn host_migrated(new_host_id: i64);
 
     #[func]
-    fn connect_to_relay(&mut self, relay_address: String, app_id: String) -> Error {
+    fn connect_to_relay(
+        &mut self,
+        relay_address: String,
+        app_id: String,
+        #[opt(default="")] game_version: GString,
+    ) -> Error {
+        if self.relay_timeout <= 0.0 {
+            godot_error!("[NodeTunnel] Invalid relay timeout: {}", self.relay_timeout);
+            return Error::from(Error::ERR_INVALID_PARAMETER);
+        }
+
         self.app_id = app_id;
+        self.game_version = game_version.to_string();
 
         let socket_addr = match relay_address.to_socket_addrs() {
             Ok(mut addrs) => match addrs.next() {
@@ -77,7 +189,7 @@ impl NodeTunnelPeer {
             }
         };
 
-        let transport = match ClientTransport::new(socket_addr) {
+        let mut transport = match ClientTransport::new(socket_addr) {
             Ok(t) => t,
             Err(e) => {
                 godot_error!("[NodeTunnel] Failed to create transport: {}", e);
@@ -87,15 +199,67 @@ impl NodeTunnelPeer {
             }
         };
 
+        transport.set_timeout(Duration::from_secs_f64(self.relay_timeout));
+        transport.set_channel_count(self.channel_count.max(1) as usize);
+
         self.relay_client.connect(transport);
+        self.relay_client.set_reconnect_policy(
+            self.auto_reconnect,
+            self.max_reconnect_attempts.max(0) as u32
+        );
         self.connection_status = ConnectionStatus::CONNECTING;
 
         Error::OK
     }
 
     #[func]
-    fn host_room(&mut self, public: bool, metadata: String) -> Error {
-        match self.relay_client.req_create_room(public, metadata) {
+    fn host_room(
+        &mut self,
+        public: bool,
+        metadata: String,
+        #[opt(default=0)] max_players: i32,
+        #[opt(default="")] password: GString,
+        #[opt(default=false)] short_code: bool,
+        #[opt(default="")] custom_code: GString,
+    ) -> Error {
+        if max_players < 0 {
+            godot_error!("[NodeTunnel] Invalid max players: {}", max_players);
+            return Error::from(Error::ERR_INVALID_PARAMETER);
+        }
+
+        // Invite codes are case-insensitive, so custom ones are sent upper-cased.
+        let custom_code = custom_code.to_string().trim().to_ascii_uppercase();
+        let invite_code = match (short_code, custom_code.is_empty()) {
+            (false, true) => InviteCode::None,
+            (true, true) => InviteCode::Short,
+            (false, false) => {
+                let valid_chars = custom_code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+                if !valid_chars || !(MIN_INVITE_CODE_LEN..=MAX_INVITE_CODE_LEN).contains(&custom_code.len()) {
+                    godot_error!(
+                        "[NodeTunnel] Invalid invite code {}, use {} to {} letters, digits or dashes",
+                        custom_code,
+                        MIN_INVITE_CODE_LEN,
+                        MAX_INVITE_CODE_LEN
+                    );
+                    return Error::from(Error::ERR_INVALID_PARAMETER);
+                }
+
+                InviteCode::Custom(custom_code)
+            }
+            (true, false) => {
+                godot_error!("[NodeTunnel] Cannot request both a short and a custom invite code");
+                return Error::from(Error::ERR_INVALID_PARAMETER);
+            }
+        };
+
+        match self.relay_client.req_create_room(
+            public,
+            metadata,
+            max_players,
+            password.to_string(),
+            self.display_name.to_string(),
+            invite_code
+        ) {
             Ok(_) => Error::OK,
             Err(e) => {
                 godot_error!("[NodeTunnel] Failed to create room: {}", e);
@@ -104,9 +268,27 @@ impl NodeTunnelPeer {
         }
     }
 
+    /// `filter` may contain `properties` (a Dictionary of listed room
+    /// properties to match), `not_full` and `game_version`.
     #[func]
-    fn get_rooms(&mut self) -> Error {
-        match self.relay_client.req_rooms() {
+    fn get_rooms(
+        &mut self,
+        #[opt(default=Dictionary::new())] filter: Dictionary,
+        #[opt(default=0)] sort: i32,
+        #[opt(default="")] cursor: GString,
+        #[opt(default=0)] page_size: i32,
+    ) -> Error {
+        let Some(sort) = RoomSort::from_i32(sort) else {
+            godot_error!("[NodeTunnel] Invalid room sort order: {}", sort);
+            return Error::from(Error::ERR_INVALID_PARAMETER);
+        };
+
+        if page_size < 0 {
+            godot_error!("[NodeTunnel] Invalid page size: {}", page_size);
+            return Error::from(Error::ERR_INVALID_PARAMETER);
+        }
+
+        match self.relay_client.req_rooms(room_filter_from_dictionary(&filter), sort, cursor.to_string(), page_size) {
             Ok(_) => {
 
                 Error::OK
@@ -118,13 +300,50 @@ impl NodeTunnelPeer {
         }
     }
 
+    /// Keeps the cached room list in sync with the relay. `filter` takes the
+    /// same keys as in `get_rooms`.
+    #[func]
+    fn subscribe_rooms(&mut self, #[opt(default=Dictionary::new())] filter: Dictionary) -> Error {
+        match self.relay_client.subscribe_rooms(room_filter_from_dictionary(&filter)) {
+            Ok(_) => Error::OK,
+            Err(e) => {
+                godot_error!("[NodeTunnel] Failed to subscribe to rooms: {}", e);
+                Error::from(Error::ERR_CANT_CONNECT)
+            }
+        }
+    }
+
+    #[func]
+    fn unsubscribe_rooms(&mut self) -> Error {
+        match self.relay_client.unsubscribe_rooms() {
+            Ok(_) => Error::OK,
+            Err(e) => {
+                godot_error!("[NodeTunnel] Failed to unsubscribe from rooms: {}", e);
+                Error::from(Error::ERR_CANT_CONNECT)
+            }
+        }
+    }
+
+    #[func]
+    fn get_cached_rooms(&self) -> Array<Gd<NodeTunnelRoomInfo>> {
+        self.relay_client.cached_rooms()
+            .map(NodeTunnelRoomInfo::from_room_info)
+            .collect()
+    }
+
+    /// `host_id` may be either the room id or its invite code.
     #[func]
     fn join_room(
         &mut self,
         host_id: String,
         #[opt(default="")] metadata: GString,
+        #[opt(default="")] password: GString,
     ) -> Error {
-        match self.relay_client.req_join_room(host_id, metadata.to_string()) {
+        // Room ids are case-sensitive, so the relay works out whether this is
+        // an invite code and only then matches it regardless of case.
+        let room_id = host_id.trim().to_string();
+
+        match self.relay_client.req_join_room(room_id, metadata.to_string(), password.to_string()) {
             Ok(_) => Error::OK,
             Err(e) => {
                 godot_error!("[NodeTunnel] Failed to join room: {}", e);
@@ -133,6 +352,27 @@ impl NodeTunnelPeer {
         }
     }
 
+    #[func]
+    fn leave_room(&mut self) -> Error {
+        if self.connection_status != ConnectionStatus::CONNECTED {
+            godot_error!("[NodeTunnel] Attempted to leave room while not in a room");
+            return Error::from(Error::ERR_UNCONFIGURED);
+        }
+
+        if let Err(e) = self.relay_client.leave_room() {
+            godot_error!("[NodeTunnel] Failed to leave room: {}", e);
+            return Error::from(Error::ERR_CONNECTION_ERROR);
+        }
+
+        // SceneMultiplayer only forgets its peers on DISCONNECTED, so they
+        // have to be reported gone before going back to CONNECTING.
+        self.disconnect_all_peers();
+        self.reset_room_state();
+        self.connection_status = ConnectionStatus::CONNECTING;
+
+        Error::OK
+    }
+
     #[func]
     fn update_room(&mut self, metadata: String) -> Error {
         match self.relay_client.req_update_room(&self.room_id.to_string(), &metadata) {
@@ -144,10 +384,280 @@ impl NodeTunnelPeer {
         }
     }
 
+    #[func]
+    fn kick_peer(
+        &mut self,
+        peer_id: i32,
+        #[opt(default="")] reason: GString,
+        #[opt(default=false)] force: bool,
+    ) -> Error {
+        if !self.is_server() {
+            godot_error!("[NodeTunnel] Only the host can kick peers");
+            return Error::from(Error::ERR_UNAUTHORIZED);
+        }
+
+        if peer_id <= 1 {
+            godot_error!("[NodeTunnel] Invalid peer to kick: {}", peer_id);
+            return Error::from(Error::ERR_INVALID_PARAMETER);
+        }
+
+        if force {
+            // Forced kicks skip whatever is still queued for the peer and
+            // never report the peer through `peer_disconnected`.
+            self.outgoing_queue.retain(|packet| packet.target != GameDataTarget::Peer(peer_id));
+            self.force_kicked_peers.push(peer_id);
+        } else {
+            self.flush_outgoing_queue();
+        }
+
+        match self.relay_client.send_kick(peer_id, reason.to_string()) {
+            Ok(_) => Error::OK,
+            Err(e) => {
+                godot_error!("[NodeTunnel] Failed to kick peer {}: {}", peer_id, e);
+                Error::from(Error::ERR_CANT_CONNECT)
+            }
+        }
+    }
+
+    #[func]
+    fn set_host_successor(&mut self, peer_id: i32) -> Error {
+        if !self.is_server() {
+            godot_error!("[NodeTunnel] Only the host can choose a successor");
+            return Error::from(Error::ERR_UNAUTHORIZED);
+        }
+
+        if peer_id <= 1 {
+            godot_error!("[NodeTunnel] Invalid host successor: {}", peer_id);
+            return Error::from(Error::ERR_INVALID_PARAMETER);
+        }
+
+        match self.relay_client.send_host_successor(peer_id) {
+            Ok(_) => Error::OK,
+            Err(e) => {
+                godot_error!("[NodeTunnel] Failed to set host successor: {}", e);
+                Error::from(Error::ERR_CANT_CONNECT)
+            }
+        }
+    }
+
+    #[func]
+    fn approve_join(&mut self, client_id: i64) -> Error {
+        self.respond_to_join(client_id as u64, true, String::new())
+    }
+
+    #[func]
+    fn reject_join(&mut self, client_id: i64, #[opt(default="")] reason: GString) -> Error {
+        self.respond_to_join(client_id as u64, false, reason.to_string())
+    }
+
+    #[func]
+    fn get_peer_metadata(&self, peer_id: i32) -> String {
+        self.peer_metadata.get(&peer_id).cloned().unwrap_or_default()
+    }
+
+    #[func]
+    fn get_peers(&self) -> PackedInt32Array {
+        let mut peers: Vec<i32> = self.peer_metadata.keys().copied().collect();
+        peers.sort_unstable();
+        PackedInt32Array::from(peers.as_slice())
+    }
+
+    #[func]
+    fn set_room_property(&mut self, key: String, value: String, #[opt(default=false)] listed: bool) -> Error {
+        self.send_room_property(key, Some(value), None, listed)
+    }
+
+    /// Only applies the change if the property still holds `expected`,
+    /// otherwise `room_property_conflict` is emitted.
+    #[func]
+    fn compare_and_set_room_property(
+        &mut self,
+        key: String,
+        expected: String,
+        value: String,
+        #[opt(default=false)] listed: bool,
+    ) -> Error {
+        self.send_room_property(key, Some(value), Some(expected), listed)
+    }
+
+    #[func]
+    fn delete_room_property(&mut self, key: String) -> Error {
+        self.send_room_property(key, None, None, false)
+    }
+
+    #[func]
+    fn get_room_property(&self, key: String) -> Variant {
+        self.room_properties.get(&key)
+            .map(|value| value.to_variant())
+            .unwrap_or_default()
+    }
+
+    #[func]
+    fn get_room_properties(&self) -> Dictionary {
+        let mut properties = Dictionary::new();
+        for (key, value) in &self.room_properties {
+            properties.set(key.clone(), value.clone());
+        }
+
+        properties
+    }
+
+    #[func]
+    fn get_rtt_ms(&self) -> f64 {
+        self.relay_client.stats().rtt.as_secs_f64() * 1000.0
+    }
+
+    #[func]
+    fn get_network_stats(&self) -> Dictionary {
+        let stats = self.relay_client.stats();
+
+        let mut stats_dict = Dictionary::new();
+        stats_dict.set("rtt_ms", stats.rtt.as_secs_f64() * 1000.0);
+        stats_dict.set("last_rtt_ms", stats.last_rtt.as_secs_f64() * 1000.0);
+        stats_dict.set("jitter_ms", stats.jitter.as_secs_f64() * 1000.0);
+        stats_dict.set("rto_ms", stats.rto().as_secs_f64() * 1000.0);
+        stats_dict.set("packet_loss", stats.packet_loss);
+        stats_dict.set("resends", stats.resends as i64);
+        stats_dict.set("packets_sent", stats.packets_sent as i64);
+        stats_dict.set("packets_received", stats.packets_received as i64);
+        stats_dict.set("bytes_sent", stats.bytes_sent as i64);
+        stats_dict.set("bytes_received", stats.bytes_received as i64);
+
+        stats_dict
+    }
+
+    fn disconnect_from_relay(&mut self) {
+        if self.relay_client.is_in_room() {
+            if let Err(e) = self.relay_client.leave_room() {
+                godot_error!("[NodeTunnel] Failed to leave room: {}", e);
+            }
+        }
+
+        if let Err(e) = self.relay_client.disconnect() {
+            godot_error!("[NodeTunnel] Failed to disconnect from relay: {}", e);
+        }
+
+        self.reset_room_state();
+        self.connection_status = ConnectionStatus::DISCONNECTED;
+    }
+
+    fn reset_room_state(&mut self) {
+        self.unique_id = 0;
+        self.room_id = "".to_godot();
+        self.invite_code = "".into();
+        self.incoming_packets.clear();
+        self.outgoing_queue.clear();
+        self.force_kicked_peers.clear();
+        self.connected_peers.clear();
+        self.pending_joins.clear();
+        self.peer_metadata.clear();
+        self.room_properties.clear();
+    }
+
+    // With server relay SceneMultiplayer routes everything through the host,
+    // so clients only see peer 1. Without it, every peer talks directly.
+    fn is_visible_peer(&self, peer_id: i32) -> bool {
+        peer_id != self.unique_id && (self.is_server() || !self.server_relay || peer_id == 1)
+    }
+
+    fn add_connected_peer(&mut self, peer_id: i32) {
+        if !self.is_visible_peer(peer_id) || self.connected_peers.contains(&peer_id) {
+            return;
+        }
+
+        self.connected_peers.push(peer_id);
+        self.signals().peer_connected().emit(peer_id as i64);
+    }
+
+    fn remove_connected_peer(&mut self, peer_id: i32) {
+        if let Some(index) = self.connected_peers.iter().position(|p| *p == peer_id) {
+            self.connected_peers.remove(index);
+            self.signals().peer_disconnected().emit(peer_id as i64);
+        }
+    }
+
+    fn disconnect_all_peers(&mut self) {
+        for peer_id in std::mem::take(&mut self.connected_peers) {
+            self.signals().peer_disconnected().emit(peer_id as i64);
+        }
+    }
+
+    fn send_room_property(&mut self, key: String, value: Option<String>, expected: Option<String>, listed: bool) -> Error {
+        match self.relay_client.req_set_room_property(key, value, expected, listed) {
+            Ok(_) => Error::OK,
+            Err(e) => {
+                godot_error!("[NodeTunnel] Failed to set room property: {}", e);
+                Error::from(Error::ERR_CANT_CONNECT)
+            }
+        }
+    }
+
+    fn send_room_locked(&mut self) {
+        if !self.is_server() || self.connection_status != ConnectionStatus::CONNECTED {
+            return;
+        }
+
+        if let Err(e) = self.relay_client.send_room_locked(self.room_id.to_string(), self.refuse_new_connections) {
+            godot_error!("[NodeTunnel] Failed to update room lock: {}", e);
+        }
+    }
+
+    fn respond_to_join(&mut self, client_id: u64, allowed: bool, message: String) -> Error {
+        if self.pending_joins.remove(&client_id).is_none() {
+            godot_error!("[NodeTunnel] No pending join request from client {}", client_id);
+            return Error::from(Error::ERR_DOES_NOT_EXIST);
+        }
+
+        self.send_join_response(client_id, allowed, message)
+    }
+
+    fn send_join_response(&mut self, client_id: u64, allowed: bool, message: String) -> Error {
+        match self.relay_client.send_join_response(self.room_id.to_string(), client_id, allowed, message) {
+            Ok(_) => Error::OK,
+            Err(e) => {
+                godot_error!("[NodeTunnel] Failed to respond to join request: {}", e);
+                Error::from(Error::ERR_CANT_CONNECT)
+            }
+        }
+    }
+
+    fn expire_join_requests(&mut self) {
+        if self.join_approval_timeout <= 0.0 {
+            return;
+        }
+
+        let timeout = Duration::from_secs_f64(self.join_approval_timeout);
+        let expired: Vec<u64> = self.pending_joins.iter()
+            .filter(|(_, requested_at)| requested_at.elapsed() >= timeout)
+            .map(|(client_id, _)| *client_id)
+            .collect();
+
+        for client_id in expired {
+            godot_warn!("[NodeTunnel] Join request from client {} timed out", client_id);
+            self.respond_to_join(client_id, false, "Join request timed out".to_string());
+        }
+    }
+
+    fn flush_outgoing_queue(&mut self) {
+        for packet in self.outgoing_queue.drain(..) {
+            match self.relay_client.send_game_data(
+                packet.target,
+                packet.data,
+                packet.channel,
+                packet.transfer_channel
+            ) {
+                Ok(_) => {},
+                Err(e) => {
+                    godot_error!("[NodeTunnel] Failed to send game data: {}", e);
+                }
+            }
+        }
+    }
+
     fn handle_relay_event(&mut self, event: RelayEvent) {
         match event {
             RelayEvent::ConnectedToServer => {
-                match self.relay_client.req_auth(self.app_id.clone()) {
+                match self.relay_client.req_auth(self.app_id.clone(), self.game_version.clone()) {
                     Err(e) => {
                         godot_error!("[NodeTunnel] Failed to authenticate: {}", e);
                         self.signals().error().emit(e.to_string());
@@ -158,63 +668,88 @@ impl NodeTunnelPeer {
             RelayEvent::Authenticated => {
                 self.signals().authenticated().emit();
             }
-            RelayEvent::RoomsReceived { rooms } => {
-                let mut room_array = Array::new();
-
-                for room in rooms {
-                    let mut room_dict = Dictionary::new();
-                    room_dict.set("id", room.id.clone());
-                    room_dict.set("metadata", room.metadata.clone());
-
-                    room_array.push(&room_dict.to_variant());
-                }
+            RelayEvent::RoomsReceived { rooms, next_cursor } => {
+                let room_array: Array<Gd<NodeTunnelRoomInfo>> = rooms.iter()
+                    .map(NodeTunnelRoomInfo::from_room_info)
+                    .collect();
 
                 self.signals().rooms_received().emit(
-                    &room_array
+                    &room_array,
+                    next_cursor
                 )
             }
-            RelayEvent::RoomJoined { room_id, peer_id } => {
+            RelayEvent::RoomJoined { room_id, peer_id, invite_code } => {
                 self.connection_status = ConnectionStatus::CONNECTED;
                 self.unique_id = peer_id;
                 self.room_id = room_id.to_godot();
+                self.invite_code = invite_code.to_godot();
 
                 if !self.is_server() {
-                    self.signals().peer_connected().emit(1);
+                    self.add_connected_peer(1);
+
+                    let roster: Vec<i32> = self.peer_metadata.keys().copied().collect();
+                    for peer_id in roster {
+                        self.add_connected_peer(peer_id);
+                    }
+                } else if self.refuse_new_connections {
+                    self.send_room_locked();
                 }
 
                 self.signals().room_connected().emit();
             },
             RelayEvent::PeerJoinAttempt { client_id, metadata } => {
                 if self.is_server() {
+                    if self.manual_join_approval && !self.join_validation.is_valid() {
+                        self.pending_joins.insert(client_id, Instant::now());
+                        self.signals().join_requested().emit(client_id as i64, metadata);
+                        return;
+                    }
+
                     let mut allowed = true;
+                    let mut message = String::new();
 
                     if self.join_validation.is_valid() {
-                        allowed = self.join_validation.call(&[metadata.to_variant()]).booleanize()
+                        // A String result rejects the join with that message.
+                        let result = self.join_validation.call(&[metadata.to_variant()]);
+                        if result.get_type() == VariantType::STRING {
+                            allowed = false;
+                            message = result.to::<String>();
+                        } else {
+                            allowed = result.booleanize();
+                        }
                     }
 
-                    self.relay_client.send_join_response(
-                        self.room_id.to_string(),
-                        client_id,
-                        allowed
-                    ).expect("todo");
+                    self.send_join_response(client_id, allowed, message);
                 }
             }
-            RelayEvent::PeerJoinedRoom { peer_id } => {
-                if self.is_server() {
-                    self.signals().peer_connected().emit(peer_id as i64);
+            RelayEvent::PeerJoinedRoom { peer_id, metadata } => {
+                self.peer_metadata.insert(peer_id, metadata);
+
+                if self.connection_status == ConnectionStatus::CONNECTED {
+                    self.add_connected_peer(peer_id);
                 }
             },
             RelayEvent::PeerLeftRoom { peer_id } => {
-                self.signals().peer_disconnected().emit(peer_id as i64);
+                self.peer_metadata.remove(&peer_id);
+
+                if let Some(index) = self.force_kicked_peers.iter().position(|p| *p == peer_id) {
+                    self.force_kicked_peers.remove(index);
+                    self.connected_peers.retain(|p| *p != peer_id);
+                    return;
+                }
+
+                self.remove_connected_peer(peer_id);
             },
-            RelayEvent::GameDataReceived { channel, from_peer, data } => {
+            RelayEvent::GameDataReceived { channel, transfer_channel, from_peer, data } => {
                 let transfer_mode = match channel {
                     Channel::Reliable => TransferMode::RELIABLE,
                     Channel::Unreliable => TransferMode::UNRELIABLE,
+                    Channel::UnreliableOrdered => TransferMode::UNRELIABLE_ORDERED,
                 };
 
                 self.incoming_packets.push(GamePacket {
                     transfer_mode,
+                    transfer_channel,
                     from_peer,
                     data
                 });
@@ -226,6 +761,119 @@ impl NodeTunnelPeer {
                     self.signals().forced_disconnect().emit();
                 }
             },
+            RelayEvent::Disconnected { reason } => {
+                godot_warn!("[NodeTunnel] Lost connection to relay: {}", reason);
+                self.reset_room_state();
+                self.connection_status = ConnectionStatus::DISCONNECTED;
+                self.signals().disconnected_from_relay().emit(reason);
+            }
+            RelayEvent::Reconnecting { attempt } => {
+                godot_warn!("[NodeTunnel] Connection to relay lost, reconnecting (attempt {})", attempt);
+                self.signals().reconnecting().emit(attempt as i64);
+            }
+            RelayEvent::Reconnected { room_id, peer_id } => {
+                if !room_id.is_empty() {
+                    self.unique_id = peer_id;
+                    self.room_id = room_id.to_godot();
+                } else if self.connection_status == ConnectionStatus::CONNECTED {
+                    // The room went away while we were gone; the session
+                    // survived, so this is the same as leaving the room.
+                    self.disconnect_all_peers();
+                    self.reset_room_state();
+                    self.connection_status = ConnectionStatus::CONNECTING;
+                }
+
+                self.signals().reconnected().emit();
+            }
+            RelayEvent::HostChanged { new_host_id } => {
+                if let Some(metadata) = self.peer_metadata.remove(&new_host_id) {
+                    self.peer_metadata.insert(1, metadata);
+                }
+
+                if new_host_id == self.unique_id {
+                    self.unique_id = 1;
+
+                    if self.refuse_new_connections {
+                        self.send_room_locked();
+                    }
+
+                    if self.connection_status == ConnectionStatus::CONNECTED {
+                        let roster: Vec<i32> = self.peer_metadata.keys().copied().collect();
+                        for peer_id in roster {
+                            self.add_connected_peer(peer_id);
+                        }
+                    }
+                } else if self.connection_status == ConnectionStatus::CONNECTED {
+                    self.remove_connected_peer(new_host_id);
+                    self.add_connected_peer(1);
+                }
+
+                self.signals().host_migrated().emit(new_host_id as i64);
+            }
+            RelayEvent::RoomUpdated { room_id, metadata } => {
+                if room_id == self.room_id.to_string() {
+                    self.signals().room_updated().emit(metadata);
+                }
+            }
+            RelayEvent::RoomListChanged { room_id, room } => {
+                let room = room
+                    .map(|room| NodeTunnelRoomInfo::from_room_info(&room).to_variant())
+                    .unwrap_or_default();
+
+                self.signals().room_list_changed().emit(room_id, &room);
+            }
+            RelayEvent::RoomPropertyChanged { key, value } => {
+                let variant = match value {
+                    Some(value) => {
+                        let variant = value.to_variant();
+                        self.room_properties.insert(key.clone(), value);
+                        variant
+                    }
+                    None => {
+                        self.room_properties.remove(&key);
+                        Variant::nil()
+                    }
+                };
+
+                self.signals().room_property_changed().emit(key, &variant);
+            }
+            RelayEvent::RoomPropertyConflict { key } => {
+                godot_warn!("[NodeTunnel] Room property {} changed before it could be set", key);
+                self.signals().room_property_conflict().emit(key);
+            }
+            RelayEvent::Kicked { reason } => {
+                godot_warn!("[NodeTunnel] Kicked from room: {}", reason);
+                self.disconnect_from_relay();
+                self.signals().kicked().emit(reason);
+            }
+            RelayEvent::RoomFull => {
+                godot_warn!("[NodeTunnel] Could not join room: room is full");
+                self.signals().room_full().emit();
+                self.signals().join_failed().emit(error_codes::ROOM_FULL as i64, "Room is full".to_string());
+            }
+            RelayEvent::VersionMismatch => {
+                godot_warn!("[NodeTunnel] Could not join room: host is running a different game version");
+                self.signals().version_mismatch().emit();
+                self.signals().join_failed().emit(error_codes::VERSION_MISMATCH as i64, "Game version mismatch".to_string());
+            }
+            RelayEvent::RoomLocked => {
+                godot_warn!("[NodeTunnel] Could not join room: room is not accepting new connections");
+                self.signals().room_locked().emit();
+                self.signals().join_failed().emit(error_codes::ROOM_LOCKED as i64, "Room is locked".to_string());
+            }
+            RelayEvent::RoomCodeTaken => {
+                godot_warn!("[NodeTunnel] Could not create room: invite code is already taken");
+                self.signals().room_code_taken().emit();
+            }
+            RelayEvent::WrongPassword => {
+                godot_warn!("[NodeTunnel] Could not join room: wrong password");
+                self.signals().wrong_password().emit();
+                self.signals().join_failed().emit(error_codes::WRONG_PASSWORD as i64, "Wrong password".to_string());
+            }
+            RelayEvent::JoinRejected { reason, message } => {
+                godot_warn!("[NodeTunnel] Join request rejected by host: {}", message);
+                self.signals().join_failed().emit(reason as i64, message);
+            }
             RelayEvent::Error { error_code, error_message } => {
                 godot_error!("[NodeTunnel] Relay error {}: {}", error_code, error_message);
                 self.signals().error().emit(error_message);
@@ -239,15 +887,32 @@ impl IMultiplayerPeerExtension for NodeTunnelPeer {
     fn init(base: Base<Self::Base>) -> Self {
         Self {
             app_id: "".to_string(),
+            game_version: "".to_string(),
             room_id: "".to_godot(),
+            invite_code: "".into(),
             join_validation: Callable::invalid(),
+            manual_join_approval: false,
+            join_approval_timeout: DEFAULT_JOIN_APPROVAL_TIMEOUT.as_secs_f64(),
+            relay_timeout: DEFAULT_TIMEOUT.as_secs_f64(),
+            auto_reconnect: true,
+            max_reconnect_attempts: DEFAULT_MAX_RECONNECT_ATTEMPTS as i32,
+            channel_count: DEFAULT_CHANNEL_COUNT as i32,
+            server_relay: true,
+            display_name: "".into(),
             unique_id: 0,
             connection_status: ConnectionStatus::DISCONNECTED,
             target_peer: 0,
             transfer_mode: TransferMode::UNRELIABLE,
+            transfer_channel: 0,
             incoming_packets: vec![],
             relay_client: RelayClient::new(),
             outgoing_queue: vec![],
+            force_kicked_peers: vec![],
+            connected_peers: vec![],
+            pending_joins: HashMap::new(),
+            refuse_new_connections: false,
+            peer_metadata: HashMap::new(),
+            room_properties: HashMap::new(),
             last_poll_time: None,
             base,
         }
@@ -278,16 +943,39 @@ impl IMultiplayerPeerExtension for NodeTunnelPeer {
             TransferMode::RELIABLE => {
                 Channel::Reliable
             },
+            TransferMode::UNRELIABLE_ORDERED => Channel::UnreliableOrdered,
             _ => Channel::Unreliable,
         };
 
-        self.outgoing_queue.push((self.target_peer, data, channel));
+        let max_size = RelayClient::max_unreliable_game_data_size(channel);
+        if channel != Channel::Reliable && data.len() > max_size {
+            godot_error!(
+                "[NodeTunnel] Unreliable packet of {} bytes exceeds the {} byte limit, use a reliable transfer mode for large packets",
+                data.len(),
+                max_size
+            );
+            return Error::from(Error::ERR_INVALID_PARAMETER);
+        }
+
+        let Some(target) = GameDataTarget::from_target_peer(self.target_peer) else {
+            godot_error!("[NodeTunnel] Invalid target peer: {}", self.target_peer);
+            return Error::from(Error::ERR_INVALID_PARAMETER);
+        };
+
+        self.outgoing_queue.push(OutgoingPacket {
+            target,
+            data,
+            channel,
+            transfer_channel: self.transfer_channel as u8,
+        });
 
         Error::OK
     }
 
     fn get_packet_channel(&self) -> i32 {
-        0
+        self.incoming_packets.first()
+            .map(|p| p.transfer_channel)
+            .unwrap_or(0)
     }
 
     fn get_packet_mode(&self) -> TransferMode {
@@ -297,13 +985,20 @@ impl IMultiplayerPeerExtension for NodeTunnelPeer {
     }
 
     fn set_transfer_channel(&mut self, p_channel: i32) {
-        if p_channel != 0 {
-            godot_warn!("[NodeTunnel] Set to invalid channel: {}", p_channel);
+        // channel_count only takes effect on the next connect_to_relay.
+        let channel_count = self.relay_client.channel_count() as i32;
+
+        if p_channel < 0 || p_channel >= channel_count {
+            godot_warn!("[NodeTunnel] Set to invalid channel {}, using channel 0", p_channel);
+            self.transfer_channel = 0;
+            return;
         }
+
+        self.transfer_channel = p_channel;
     }
 
     fn get_transfer_channel(&self) -> i32 {
-        0
+        self.transfer_channel
     }
 
     fn set_transfer_mode(&mut self, p_mode: TransferMode) {
@@ -329,6 +1024,10 @@ impl IMultiplayerPeerExtension for NodeTunnelPeer {
     }
 
     fn poll(&mut self) {
+        if !self.relay_client.is_active() {
+            return;
+        }
+
         let now = Instant::now();
         let delta = match self.last_poll_time {
             Some(last) => now.duration_since(last),
@@ -347,37 +1046,71 @@ impl IMultiplayerPeerExtension for NodeTunnelPeer {
             }
         }
 
-        for (peer, data, channel) in self.outgoing_queue.drain(..) {
-            match self.relay_client.send_game_data(peer, data, channel) {
-                Ok(_) => {},
-                Err(e) => {
-                    godot_error!("[NodeTunnel] Failed to send game data: {}", e);
-                }
-            }
-        }
+        self.expire_join_requests();
+        self.flush_outgoing_queue();
     }
 
     fn close(&mut self) {
-        if self.connection_status == ConnectionStatus::DISCONNECTED || !self.relay_client.is_connected() {
+        if self.connection_status == ConnectionStatus::DISCONNECTED && !self.relay_client.is_active() {
             godot_warn!("[NodeTunnel] Attempted to close connection while disconnected");
             return;
         }
 
-        self.unique_id = 0;
-        self.connection_status = ConnectionStatus::DISCONNECTED;
+        self.disconnect_from_relay();
     }
 
-    fn disconnect_peer(&mut self, _p_peer: i32, _p_force: bool) {}
+    fn disconnect_peer(&mut self, p_peer: i32, p_force: bool) {
+        self.kick_peer(p_peer, "".into(), p_force);
+    }
 
     fn get_unique_id(&self) -> i32 {
         self.unique_id
     }
 
+    fn set_refuse_new_connections(&mut self, p_enable: bool) {
+        if self.refuse_new_connections == p_enable {
+            return;
+        }
+
+        self.refuse_new_connections = p_enable;
+        self.send_room_locked();
+    }
+
+    fn is_refusing_new_connections(&self) -> bool {
+        self.refuse_new_connections
+    }
+
     fn is_server_relay_supported(&self) -> bool {
-        true
+        self.server_relay
     }
 
     fn get_connection_status(&self) -> ConnectionStatus {
         self.connection_status
     }
 }
+
+fn room_filter_from_dictionary(filter: &Dictionary) -> RoomFilter {
+    let mut room_filter = RoomFilter::default();
+
+    if let Some(properties) = filter.get("properties").and_then(|p| p.try_to::<Dictionary>().ok()) {
+        room_filter.properties = properties.iter_shared()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+    }
+    if let Some(not_full) = filter.get("not_full") {
+        room_filter.not_full = not_full.booleanize();
+    }
+    if let Some(game_version) = filter.get("game_version") {
+        room_filter.game_version = game_version.to_string();
+    }
+
+    room_filter
+}
+
+impl Drop for NodeTunnelPeer {
+    fn drop(&mut self) {
+        if self.relay_client.is_active() {
+            self.disconnect_from_relay();
+        }
+    }
+}
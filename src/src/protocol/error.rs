@@ -18,5 +18,14 @@ pub enum ProtocolError {
     InvalidUtf8String(#[from] std::string::FromUtf8Error),
 
     #[error("Negative vector length")]
-    NegativeVectorLength()
+    NegativeVectorLength(),
+
+    #[error("Invalid game data target: {0}")]
+    InvalidTarget(i32),
+
+    #[error("Invalid room sort order: {0}")]
+    InvalidRoomSort(i32),
+
+    #[error("Invalid invite code kind: {0}")]
+    InvalidInviteCode(i32)
 }
\ No newline at end of file
@@ -0,0 +1,8 @@
+pub const ROOM_FULL: i32 = 100;
+pub const RESUME_FAILED: i32 = 101;
+pub const WRONG_PASSWORD: i32 = 102;
+pub const JOIN_REJECTED: i32 = 103;
+pub const PROPERTY_CONFLICT: i32 = 104;
+pub const ROOM_CODE_TAKEN: i32 = 105;
+pub const ROOM_LOCKED: i32 = 106;
+pub const VERSION_MISMATCH: i32 = 107;
\ No newline at end of file
@@ -12,4 +12,20 @@ pub const REQ_ROOMS: u8 = 10;
 pub const GET_ROOMS: u8 = 11;
 pub const UPDATE_ROOM: u8 = 12;
 pub const JOIN_RES: u8 = 13;
-pub const PEER_JOIN_ATTEMPT: u8 = 14;
\ No newline at end of file
+pub const PEER_JOIN_ATTEMPT: u8 = 14;
+pub const KICK_PEER: u8 = 15;
+pub const LEAVE_ROOM: u8 = 16;
+pub const DISCONNECT: u8 = 17;
+pub const SESSION_RESUMED: u8 = 18;
+pub const SEND_GAME_DATA: u8 = 19;
+pub const HOST_CHANGED: u8 = 20;
+pub const SET_HOST_SUCCESSOR: u8 = 21;
+pub const SET_ROOM_PROPERTY: u8 = 22;
+pub const ROOM_PROPERTY_CHANGED: u8 = 23;
+pub const SUBSCRIBE_ROOMS: u8 = 24;
+pub const UNSUBSCRIBE_ROOMS: u8 = 25;
+pub const ROOM_LIST_ADDED: u8 = 26;
+pub const ROOM_LIST_UPDATED: u8 = 27;
+pub const ROOM_LIST_REMOVED: u8 = 28;
+pub const ROOM_UPDATED: u8 = 29;
+pub const SET_ROOM_LOCKED: u8 = 30;
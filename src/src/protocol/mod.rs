@@ -2,4 +2,5 @@ mod ids;
 pub mod packet;
 mod serialize;
 pub mod version;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod error_codes;
\ No newline at end of file
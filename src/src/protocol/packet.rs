@@ -1,29 +1,145 @@
 use crate::protocol::ids::*;
 use crate::protocol::error::ProtocolError;
-use crate::protocol::serialize::{push_bool, push_i32, push_string, push_u64, push_vec_room_info, read_bool, read_i32, read_string, read_u64, read_vec_room_info};
+use crate::protocol::serialize::{push_bool, push_i32, push_invite_code, push_opt_string, push_room_filter, push_room_info, push_string, push_target, push_u64, push_vec_room_info, push_vec_u32, read_bool, read_i32, read_invite_code, read_opt_string, read_room_filter, read_room_info, read_room_sort, read_string, read_target, read_u64, read_vec_room_info, read_vec_u32};
 
 #[derive(Debug, Clone)]
 pub struct RoomInfo {
     pub id: String,
     pub metadata: String,
+    pub player_count: i32,
+    pub max_players: i32,
+    /// Room properties flagged as listed.
+    pub properties: Vec<(String, String)>,
+    pub host_name: String,
+    /// Unix timestamp in seconds.
+    pub created_at: u64,
+    pub region: String,
+    pub has_password: bool,
+    /// The relay's estimate of the latency to the host, 0 when unknown.
+    pub latency_hint_ms: i32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RoomFilter {
+    /// Listed room properties that must match exactly.
+    pub properties: Vec<(String, String)>,
+    pub not_full: bool,
+    /// Empty uses the game version sent when authenticating.
+    pub game_version: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoomSort {
+    #[default]
+    Default = 0,
+    MostPlayers = 1,
+    FewestPlayers = 2,
+    Newest = 3,
+    Oldest = 4,
+}
+
+impl RoomSort {
+    pub fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(RoomSort::Default),
+            1 => Some(RoomSort::MostPlayers),
+            2 => Some(RoomSort::FewestPlayers),
+            3 => Some(RoomSort::Newest),
+            4 => Some(RoomSort::Oldest),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum InviteCode {
+    #[default]
+    None,
+    /// A short code picked by the relay from an alphabet without
+    /// look-alike characters.
+    Short,
+    Custom(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameDataTarget {
+    Broadcast,
+    Peer(i32),
+    AllExcept(i32),
+}
+
+impl GameDataTarget {
+    /// Maps Godot's target peer convention: 0 is everyone, a negative id is
+    /// everyone except that peer, and a positive id is that peer alone.
+    /// `i32::MIN` names no peer and gives `None`.
+    pub fn from_target_peer(target_peer: i32) -> Option<Self> {
+        match target_peer {
+            0 => Some(GameDataTarget::Broadcast),
+            t if t < 0 => t.checked_neg().map(GameDataTarget::AllExcept),
+            t => Some(GameDataTarget::Peer(t)),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum PacketType {
-    Authenticate { app_id: String, version: String },
-    ClientAuthenticated,
-    CreateRoom { is_public: bool, metadata: String },
-    ReqRooms,
-    GetRooms { rooms: Vec<RoomInfo> },
+    /// Rooms are tagged with the host's `game_version`. The relay only lists
+    /// rooms of the same version and rejects joins across versions.
+    /// `channel_count` is how many game transfer channels the client uses,
+    /// so the relay can size its lanes for this client to match.
+    Authenticate { app_id: String, version: String, resume_token: String, game_version: String, channel_count: i32 },
+    ClientAuthenticated { resume_token: String },
+    CreateRoom {
+        is_public: bool,
+        metadata: String,
+        max_players: i32,
+        password: String,
+        host_name: String,
+        invite_code: InviteCode,
+    },
+    /// A `limit` of 0 lets the relay pick the page size.
+    ReqRooms { filter: RoomFilter, sort: RoomSort, cursor: String, limit: i32 },
+    /// An empty `next_cursor` marks the last page.
+    GetRooms { rooms: Vec<RoomInfo>, next_cursor: String },
     UpdateRoom { room_id: String, metadata: String },
-    ReqJoin { room_id: String, metadata: String },
-    JoinRes { target_id: u64, room_id: String, allowed: bool },
-    ConnectedToRoom { room_id: String, peer_id: i32 },
+    RoomUpdated { room_id: String, metadata: String },
+    /// `room_id` may also be an invite code, which the relay matches
+    /// regardless of case.
+    ReqJoin { room_id: String, metadata: String, password: String },
+    JoinRes { target_id: u64, room_id: String, allowed: bool, reason: i32, message: String },
+    /// `invite_code` is empty unless the room was created with one.
+    ConnectedToRoom { room_id: String, peer_id: i32, resume_token: String, invite_code: String },
+    /// `received_seqs[lane]` is the first reliable sequence number the relay
+    /// was still missing on that lane when the old connection dropped.
+    SessionResumed { room_id: String, peer_id: i32, resume_token: String, received_seqs: Vec<u32> },
     PeerJoinAttempt { target_id: u64, metadata: String },
-    PeerJoinedRoom { peer_id: i32 },
+    PeerJoinedRoom { peer_id: i32, metadata: String },
     PeerLeftRoom { peer_id: i32 },
-    GameData { from_peer: i32, data: Vec<u8> },
+    SendGameData { target: GameDataTarget, transfer_channel: i32, data: Vec<u8> },
+    GameData { from_peer: i32, transfer_channel: i32, data: Vec<u8> },
     ForceDisconnect,
+    KickPeer { peer_id: i32, reason: String },
+    LeaveRoom,
+    Disconnect,
+    /// Follows the PeerLeftRoom for the old host. From here on the relay
+    /// refers to `new_host_id` as peer 1.
+    HostChanged { new_host_id: i32 },
+    SetHostSuccessor { peer_id: i32 },
+    /// A locked room rejects joins and is left out of room listings.
+    SetRoomLocked { room_id: String, locked: bool },
+    /// A `None` value deletes the key. With `expected` set, the relay only
+    /// applies the change if the current value matches.
+    SetRoomProperty { key: String, value: Option<String>, expected: Option<String>, listed: bool },
+    /// Right after `ConnectedToRoom` the relay sends one of these for every
+    /// key already set, so a joiner starts from the full property set.
+    RoomPropertyChanged { key: String, value: Option<String> },
+    /// The relay answers a subscription with a `RoomListAdded` for every
+    /// matching room, then keeps sending deltas until unsubscribed.
+    SubscribeRooms { filter: RoomFilter },
+    UnsubscribeRooms,
+    RoomListAdded { room: RoomInfo },
+    RoomListUpdated { room: RoomInfo },
+    RoomListRemoved { room_id: String },
     Error { error_code: i32, error_message: String }
 }
 
@@ -39,36 +155,69 @@ impl PacketType {
         Ok(match packet_id {
             AUTHENTICATE => {
                 let (app_id, r) = read_string(rest)?;
-                let (version, _) = read_string(r)?;
-                PacketType::Authenticate { app_id, version }
+                let (version, r) = read_string(r)?;
+                let (resume_token, r) = read_string(r)?;
+                let (game_version, r) = read_string(r)?;
+                let (channel_count, _) = read_i32(r)?;
+                PacketType::Authenticate { app_id, version, resume_token, game_version, channel_count }
             }
 
-            CLIENT_AUTHENTICATED => PacketType::ClientAuthenticated,
+            CLIENT_AUTHENTICATED => {
+                let (resume_token, _) = read_string(rest)?;
+                PacketType::ClientAuthenticated { resume_token }
+            }
 
             CREATE_ROOM => {
                 let (is_public, r) = read_bool(rest)?;
-                let metadata = match read_string(r) {
-                    Ok((name, _)) => {
-                        name
+                let (metadata, r) = match read_string(r) {
+                    Ok((name, r)) => {
+                        (name, r)
                     }
                     Err(_) => {
-                        "".into()
+                        ("".into(), r)
                     }
                 };
+                let (max_players, r) = match read_i32(r) {
+                    Ok((max_players, r)) => (max_players, r),
+                    Err(_) => (0, r),
+                };
+                let (password, r) = match read_string(r) {
+                    Ok((password, r)) => (password, r),
+                    Err(_) => ("".into(), r),
+                };
+                let (host_name, r) = match read_string(r) {
+                    Ok((host_name, r)) => (host_name, r),
+                    Err(_) => ("".into(), r),
+                };
+                let invite_code = match read_invite_code(r) {
+                    Ok((invite_code, _)) => invite_code,
+                    Err(_) => InviteCode::None,
+                };
 
-                PacketType::CreateRoom { is_public, metadata }
+                PacketType::CreateRoom { is_public, metadata, max_players, password, host_name, invite_code }
             },
 
             JOIN_ROOM => {
                 let (room_id, r) = read_string(rest)?;
-                let (metadata, _) = read_string(r)?;
-                PacketType::ReqJoin { room_id, metadata }
+                let (metadata, r) = read_string(r)?;
+                let (password, _) = read_string(r)?;
+                PacketType::ReqJoin { room_id, metadata, password }
             }
 
             CONNECTED_TO_ROOM => {
                 let (room_id, r) = read_string(rest)?;
-                let (peer_id, _) = read_i32(r)?;
-                PacketType::ConnectedToRoom { room_id, peer_id }
+                let (peer_id, r) = read_i32(r)?;
+                let (resume_token, r) = read_string(r)?;
+                let (invite_code, _) = read_string(r)?;
+                PacketType::ConnectedToRoom { room_id, peer_id, resume_token, invite_code }
+            }
+
+            SESSION_RESUMED => {
+                let (room_id, r) = read_string(rest)?;
+                let (peer_id, r) = read_i32(r)?;
+                let (resume_token, r) = read_string(r)?;
+                let (received_seqs, _) = read_vec_u32(r)?;
+                PacketType::SessionResumed { room_id, peer_id, resume_token, received_seqs }
             }
 
             PEER_JOIN_ATTEMPT => {
@@ -78,8 +227,9 @@ impl PacketType {
             }
 
             PEER_JOINED => {
-                let (peer_id, _) = read_i32(rest)?;
-                PacketType::PeerJoinedRoom { peer_id }
+                let (peer_id, r) = read_i32(rest)?;
+                let (metadata, _) = read_string(r)?;
+                PacketType::PeerJoinedRoom { peer_id, metadata }
             }
 
             PEER_LEFT => {
@@ -89,22 +239,98 @@ impl PacketType {
 
             GAME_DATA => {
                 let (peer_id, r) = read_i32(rest)?;
-                PacketType::GameData { from_peer: peer_id, data: r.to_vec() }
+                let (transfer_channel, r) = read_i32(r)?;
+                PacketType::GameData { from_peer: peer_id, transfer_channel, data: r.to_vec() }
+            }
+
+            SEND_GAME_DATA => {
+                let (target, r) = read_target(rest)?;
+                let (transfer_channel, r) = read_i32(r)?;
+                PacketType::SendGameData { target, transfer_channel, data: r.to_vec() }
             }
 
             FORCE_DISCONNECT => PacketType::ForceDisconnect,
 
+            KICK_PEER => {
+                let (peer_id, r) = read_i32(rest)?;
+                let (reason, _) = read_string(r)?;
+                PacketType::KickPeer { peer_id, reason }
+            }
+
+            LEAVE_ROOM => PacketType::LeaveRoom,
+
+            DISCONNECT => PacketType::Disconnect,
+
+            HOST_CHANGED => {
+                let (new_host_id, _) = read_i32(rest)?;
+                PacketType::HostChanged { new_host_id }
+            }
+
+            SET_HOST_SUCCESSOR => {
+                let (peer_id, _) = read_i32(rest)?;
+                PacketType::SetHostSuccessor { peer_id }
+            }
+
+            SET_ROOM_LOCKED => {
+                let (room_id, r) = read_string(rest)?;
+                let (locked, _) = read_bool(r)?;
+                PacketType::SetRoomLocked { room_id, locked }
+            }
+
+            SET_ROOM_PROPERTY => {
+                let (key, r) = read_string(rest)?;
+                let (value, r) = read_opt_string(r)?;
+                let (expected, r) = read_opt_string(r)?;
+                let (listed, _) = read_bool(r)?;
+                PacketType::SetRoomProperty { key, value, expected, listed }
+            }
+
+            ROOM_PROPERTY_CHANGED => {
+                let (key, r) = read_string(rest)?;
+                let (value, _) = read_opt_string(r)?;
+                PacketType::RoomPropertyChanged { key, value }
+            }
+
+            SUBSCRIBE_ROOMS => {
+                let (filter, _) = read_room_filter(rest)?;
+                PacketType::SubscribeRooms { filter }
+            }
+
+            UNSUBSCRIBE_ROOMS => PacketType::UnsubscribeRooms,
+
+            ROOM_LIST_ADDED => {
+                let (room, _) = read_room_info(rest)?;
+                PacketType::RoomListAdded { room }
+            }
+
+            ROOM_LIST_UPDATED => {
+                let (room, _) = read_room_info(rest)?;
+                PacketType::RoomListUpdated { room }
+            }
+
+            ROOM_LIST_REMOVED => {
+                let (room_id, _) = read_string(rest)?;
+                PacketType::RoomListRemoved { room_id }
+            }
+
             ERROR_PACKET => {
                 let (error_code, r) = read_i32(rest)?;
                 let (error_message, _) = read_string(r)?;
                 PacketType::Error { error_code, error_message }
             }
 
-            REQ_ROOMS => PacketType::ReqRooms,
+            REQ_ROOMS => {
+                let (filter, r) = read_room_filter(rest)?;
+                let (sort, r) = read_room_sort(r)?;
+                let (cursor, r) = read_string(r)?;
+                let (limit, _) = read_i32(r)?;
+                PacketType::ReqRooms { filter, sort, cursor, limit }
+            }
 
             GET_ROOMS => {
-                let (rooms, _) = read_vec_room_info(rest)?;
-                PacketType::GetRooms { rooms }
+                let (rooms, r) = read_vec_room_info(rest)?;
+                let (next_cursor, _) = read_string(r)?;
+                PacketType::GetRooms { rooms, next_cursor }
             }
 
             UPDATE_ROOM => {
@@ -113,11 +339,19 @@ impl PacketType {
                 PacketType::UpdateRoom { room_id, metadata }
             }
 
+            ROOM_UPDATED => {
+                let (room_id, r) = read_string(rest)?;
+                let (metadata, _) = read_string(r)?;
+                PacketType::RoomUpdated { room_id, metadata }
+            }
+
             JOIN_RES => {
                 let (target_id, r) = read_u64(rest)?;
                 let (room_id, r) = read_string(r)?;
-                let (allowed, _) = read_bool(r)?;
-                PacketType::JoinRes { target_id, room_id, allowed }
+                let (allowed, r) = read_bool(r)?;
+                let (reason, r) = read_i32(r)?;
+                let (message, _) = read_string(r)?;
+                PacketType::JoinRes { target_id, room_id, allowed, reason, message }
             }
 
             _ => return Err(ProtocolError::UnknownPacketType(packet_id))
@@ -128,29 +362,42 @@ impl PacketType {
         let mut buf = Vec::new();
 
         match self {
-            PacketType::Authenticate { app_id, version } => {
+            PacketType::Authenticate { app_id, version, resume_token, game_version, channel_count } => {
                 buf.push(AUTHENTICATE);
                 push_string(&mut buf, app_id);
                 push_string(&mut buf, version);
+                push_string(&mut buf, resume_token);
+                push_string(&mut buf, game_version);
+                push_i32(&mut buf, *channel_count);
             }
 
-            PacketType::ClientAuthenticated => {
+            PacketType::ClientAuthenticated { resume_token } => {
                 buf.push(CLIENT_AUTHENTICATED);
+                push_string(&mut buf, resume_token);
             }
 
-            PacketType::CreateRoom { is_public, metadata } => {
+            PacketType::CreateRoom { is_public, metadata, max_players, password, host_name, invite_code } => {
                 buf.push(CREATE_ROOM);
                 push_bool(&mut buf, *is_public);
                 push_string(&mut buf, metadata);
+                push_i32(&mut buf, *max_players);
+                push_string(&mut buf, password);
+                push_string(&mut buf, host_name);
+                push_invite_code(&mut buf, invite_code);
             }
 
-            PacketType::ReqRooms => {
+            PacketType::ReqRooms { filter, sort, cursor, limit } => {
                 buf.push(REQ_ROOMS);
+                push_room_filter(&mut buf, filter);
+                push_i32(&mut buf, *sort as i32);
+                push_string(&mut buf, cursor);
+                push_i32(&mut buf, *limit);
             }
 
-            PacketType::GetRooms { rooms } => {
+            PacketType::GetRooms { rooms, next_cursor } => {
                 buf.push(GET_ROOMS);
                 push_vec_room_info(&mut buf, rooms);
+                push_string(&mut buf, next_cursor);
             }
 
             PacketType::UpdateRoom { room_id, metadata } => {
@@ -159,23 +406,42 @@ impl PacketType {
                 push_string(&mut buf, metadata);
             }
 
-            PacketType::ReqJoin { room_id, metadata } => {
+            PacketType::RoomUpdated { room_id, metadata } => {
+                buf.push(ROOM_UPDATED);
+                push_string(&mut buf, room_id);
+                push_string(&mut buf, metadata);
+            }
+
+            PacketType::ReqJoin { room_id, metadata, password } => {
                 buf.push(JOIN_ROOM);
                 push_string(&mut buf, room_id);
                 push_string(&mut buf, metadata);
+                push_string(&mut buf, password);
             }
 
-            PacketType::JoinRes { target_id, room_id, allowed } => {
+            PacketType::JoinRes { target_id, room_id, allowed, reason, message } => {
                 buf.push(JOIN_RES);
                 push_u64(&mut buf, *target_id);
                 push_string(&mut buf, room_id);
                 push_bool(&mut buf, *allowed);
+                push_i32(&mut buf, *reason);
+                push_string(&mut buf, message);
             }
 
-            PacketType::ConnectedToRoom { room_id, peer_id } => {
+            PacketType::ConnectedToRoom { room_id, peer_id, resume_token, invite_code } => {
                 buf.push(CONNECTED_TO_ROOM);
                 push_string(&mut buf, room_id);
                 push_i32(&mut buf, *peer_id);
+                push_string(&mut buf, resume_token);
+                push_string(&mut buf, invite_code);
+            }
+
+            PacketType::SessionResumed { room_id, peer_id, resume_token, received_seqs } => {
+                buf.push(SESSION_RESUMED);
+                push_string(&mut buf, room_id);
+                push_i32(&mut buf, *peer_id);
+                push_string(&mut buf, resume_token);
+                push_vec_u32(&mut buf, received_seqs);
             }
 
             PacketType::PeerJoinAttempt { target_id, metadata } => {
@@ -184,9 +450,10 @@ impl PacketType {
                 push_string(&mut buf, metadata);
             }
 
-            PacketType::PeerJoinedRoom { peer_id } => {
+            PacketType::PeerJoinedRoom { peer_id, metadata } => {
                 buf.push(PEER_JOINED);
                 push_i32(&mut buf, *peer_id);
+                push_string(&mut buf, metadata);
             }
 
             PacketType::PeerLeftRoom { peer_id } => {
@@ -194,9 +461,17 @@ impl PacketType {
                 push_i32(&mut buf, *peer_id);
             }
 
-            PacketType::GameData { from_peer: peer_id, data } => {
+            PacketType::GameData { from_peer: peer_id, transfer_channel, data } => {
                 buf.push(GAME_DATA);
                 push_i32(&mut buf, *peer_id);
+                push_i32(&mut buf, *transfer_channel);
+                buf.extend(data);
+            }
+
+            PacketType::SendGameData { target, transfer_channel, data } => {
+                buf.push(SEND_GAME_DATA);
+                push_target(&mut buf, target);
+                push_i32(&mut buf, *transfer_channel);
                 buf.extend(data);
             }
 
@@ -204,6 +479,74 @@ impl PacketType {
                 buf.push(FORCE_DISCONNECT);
             }
 
+            PacketType::KickPeer { peer_id, reason } => {
+                buf.push(KICK_PEER);
+                push_i32(&mut buf, *peer_id);
+                push_string(&mut buf, reason);
+            }
+
+            PacketType::LeaveRoom => {
+                buf.push(LEAVE_ROOM);
+            }
+
+            PacketType::Disconnect => {
+                buf.push(DISCONNECT);
+            }
+
+            PacketType::HostChanged { new_host_id } => {
+                buf.push(HOST_CHANGED);
+                push_i32(&mut buf, *new_host_id);
+            }
+
+            PacketType::SetHostSuccessor { peer_id } => {
+                buf.push(SET_HOST_SUCCESSOR);
+                push_i32(&mut buf, *peer_id);
+            }
+
+            PacketType::SetRoomLocked { room_id, locked } => {
+                buf.push(SET_ROOM_LOCKED);
+                push_string(&mut buf, room_id);
+                push_bool(&mut buf, *locked);
+            }
+
+            PacketType::SetRoomProperty { key, value, expected, listed } => {
+                buf.push(SET_ROOM_PROPERTY);
+                push_string(&mut buf, key);
+                push_opt_string(&mut buf, value.as_deref());
+                push_opt_string(&mut buf, expected.as_deref());
+                push_bool(&mut buf, *listed);
+            }
+
+            PacketType::RoomPropertyChanged { key, value } => {
+                buf.push(ROOM_PROPERTY_CHANGED);
+                push_string(&mut buf, key);
+                push_opt_string(&mut buf, value.as_deref());
+            }
+
+            PacketType::SubscribeRooms { filter } => {
+                buf.push(SUBSCRIBE_ROOMS);
+                push_room_filter(&mut buf, filter);
+            }
+
+            PacketType::UnsubscribeRooms => {
+                buf.push(UNSUBSCRIBE_ROOMS);
+            }
+
+            PacketType::RoomListAdded { room } => {
+                buf.push(ROOM_LIST_ADDED);
+                push_room_info(&mut buf, room);
+            }
+
+            PacketType::RoomListUpdated { room } => {
+                buf.push(ROOM_LIST_UPDATED);
+                push_room_info(&mut buf, room);
+            }
+
+            PacketType::RoomListRemoved { room_id } => {
+                buf.push(ROOM_LIST_REMOVED);
+                push_string(&mut buf, room_id);
+            }
+
             PacketType::Error { error_code, error_message } => {
                 buf.push(ERROR_PACKET);
                 push_i32(&mut buf, *error_code);
@@ -1,5 +1,5 @@
 use crate::protocol::error::ProtocolError;
-use crate::protocol::packet::RoomInfo;
+use crate::protocol::packet::{GameDataTarget, InviteCode, RoomFilter, RoomInfo, RoomSort};
 
 pub fn read_bool(bytes: &[u8]) -> Result<(bool, &[u8]), ProtocolError> {
     let (value, rest) = read_i32(bytes)?;
@@ -42,6 +42,23 @@ pub fn read_string(bytes: &[u8]) -> Result<(String, &[u8]), ProtocolError> {
     Ok((String::from_utf8(string_bytes.to_vec())?, remaining))
 }
 
+pub fn read_opt_string(bytes: &[u8]) -> Result<(Option<String>, &[u8]), ProtocolError> {
+    let (present, r) = read_bool(bytes)?;
+    if !present {
+        return Ok((None, r));
+    }
+
+    let (value, r) = read_string(r)?;
+    Ok((Some(value), r))
+}
+
+pub fn push_opt_string(buf: &mut Vec<u8>, value: Option<&str>) {
+    push_bool(buf, value.is_some());
+    if let Some(value) = value {
+        push_string(buf, value);
+    }
+}
+
 pub fn push_string(buf: &mut Vec<u8>, value: &str) {
     let bytes = value.as_bytes();
     buf.extend((bytes.len() as i32).to_be_bytes());
@@ -58,11 +75,88 @@ pub fn push_i32(buf: &mut Vec<u8>, value: i32) {
 
 pub fn push_u64(buf: &mut Vec<u8>, value: u64) { buf.extend(value.to_be_bytes()) }
 
+const TARGET_BROADCAST: i32 = 0;
+const TARGET_PEER: i32 = 1;
+const TARGET_ALL_EXCEPT: i32 = 2;
+
+pub fn read_target(bytes: &[u8]) -> Result<(GameDataTarget, &[u8]), ProtocolError> {
+    let (kind, r) = read_i32(bytes)?;
+    let (peer_id, r) = read_i32(r)?;
+
+    let target = match kind {
+        TARGET_BROADCAST => GameDataTarget::Broadcast,
+        TARGET_PEER => GameDataTarget::Peer(peer_id),
+        TARGET_ALL_EXCEPT => GameDataTarget::AllExcept(peer_id),
+        _ => return Err(ProtocolError::InvalidTarget(kind)),
+    };
+
+    Ok((target, r))
+}
+
+pub fn push_target(buf: &mut Vec<u8>, target: &GameDataTarget) {
+    let (kind, peer_id) = match target {
+        GameDataTarget::Broadcast => (TARGET_BROADCAST, 0),
+        GameDataTarget::Peer(peer_id) => (TARGET_PEER, *peer_id),
+        GameDataTarget::AllExcept(peer_id) => (TARGET_ALL_EXCEPT, *peer_id),
+    };
+
+    push_i32(buf, kind);
+    push_i32(buf, peer_id);
+}
+
+const INVITE_CODE_NONE: i32 = 0;
+const INVITE_CODE_SHORT: i32 = 1;
+const INVITE_CODE_CUSTOM: i32 = 2;
+
+pub fn read_invite_code(bytes: &[u8]) -> Result<(InviteCode, &[u8]), ProtocolError> {
+    let (kind, r) = read_i32(bytes)?;
+    let (code, r) = read_string(r)?;
+
+    let invite_code = match kind {
+        INVITE_CODE_NONE => InviteCode::None,
+        INVITE_CODE_SHORT => InviteCode::Short,
+        INVITE_CODE_CUSTOM => InviteCode::Custom(code),
+        _ => return Err(ProtocolError::InvalidInviteCode(kind)),
+    };
+
+    Ok((invite_code, r))
+}
+
+pub fn push_invite_code(buf: &mut Vec<u8>, invite_code: &InviteCode) {
+    let (kind, code) = match invite_code {
+        InviteCode::None => (INVITE_CODE_NONE, ""),
+        InviteCode::Short => (INVITE_CODE_SHORT, ""),
+        InviteCode::Custom(code) => (INVITE_CODE_CUSTOM, code.as_str()),
+    };
+
+    push_i32(buf, kind);
+    push_string(buf, code);
+}
+
 pub fn read_room_info(bytes: &[u8]) -> Result<(RoomInfo, &[u8]), ProtocolError> {
     let (id, r) = read_string(bytes)?;
     let (metadata, r) = read_string(r)?;
+    let (player_count, r) = read_i32(r)?;
+    let (max_players, r) = read_i32(r)?;
+    let (properties, r) = read_string_pairs(r)?;
+    let (host_name, r) = read_string(r)?;
+    let (created_at, r) = read_u64(r)?;
+    let (region, r) = read_string(r)?;
+    let (has_password, r) = read_bool(r)?;
+    let (latency_hint_ms, r) = read_i32(r)?;
 
-    Ok((RoomInfo { id, metadata }, r))
+    Ok((RoomInfo {
+        id,
+        metadata,
+        player_count,
+        max_players,
+        properties,
+        host_name,
+        created_at,
+        region,
+        has_password,
+        latency_hint_ms
+    }, r))
 }
 
 pub fn read_vec_room_info(bytes: &[u8]) -> Result<(Vec<RoomInfo>, &[u8]), ProtocolError> {
@@ -82,10 +176,94 @@ pub fn read_vec_room_info(bytes: &[u8]) -> Result<(Vec<RoomInfo>, &[u8]), Protoc
     Ok((rooms, rest))
 }
 
+pub fn read_vec_u32(bytes: &[u8]) -> Result<(Vec<u32>, &[u8]), ProtocolError> {
+    let (len, mut rest) = read_i32(bytes)?;
+
+    if len < 0 {
+        return Err(ProtocolError::NegativeVectorLength());
+    }
+
+    let mut values = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        let (value, remaining) = read_i32(rest)?;
+        values.push(value as u32);
+        rest = remaining;
+    }
+
+    Ok((values, rest))
+}
+
+pub fn push_vec_u32(buf: &mut Vec<u8>, values: &[u32]) {
+    push_i32(buf, values.len() as i32);
+    for value in values {
+        push_i32(buf, *value as i32);
+    }
+}
+
 pub fn push_vec_room_info(buf: &mut Vec<u8>, rooms: &[RoomInfo]) {
     push_i32(buf, rooms.len() as i32);
     for room in rooms {
-        push_string(buf, &room.id);
-        push_string(buf, &room.metadata);
+        push_room_info(buf, room);
     }
 }
+
+pub fn push_room_info(buf: &mut Vec<u8>, room: &RoomInfo) {
+    push_string(buf, &room.id);
+    push_string(buf, &room.metadata);
+    push_i32(buf, room.player_count);
+    push_i32(buf, room.max_players);
+    push_string_pairs(buf, &room.properties);
+    push_string(buf, &room.host_name);
+    push_u64(buf, room.created_at);
+    push_string(buf, &room.region);
+    push_bool(buf, room.has_password);
+    push_i32(buf, room.latency_hint_ms);
+}
+
+type StringPairs = Vec<(String, String)>;
+
+pub fn read_string_pairs(bytes: &[u8]) -> Result<(StringPairs, &[u8]), ProtocolError> {
+    let (len, mut rest) = read_i32(bytes)?;
+
+    if len < 0 {
+        return Err(ProtocolError::NegativeVectorLength());
+    }
+
+    let mut pairs = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        let (key, r) = read_string(rest)?;
+        let (value, r) = read_string(r)?;
+        pairs.push((key, value));
+        rest = r;
+    }
+
+    Ok((pairs, rest))
+}
+
+pub fn push_string_pairs(buf: &mut Vec<u8>, pairs: &[(String, String)]) {
+    push_i32(buf, pairs.len() as i32);
+    for (key, value) in pairs {
+        push_string(buf, key);
+        push_string(buf, value);
+    }
+}
+
+pub fn read_room_filter(bytes: &[u8]) -> Result<(RoomFilter, &[u8]), ProtocolError> {
+    let (properties, r) = read_string_pairs(bytes)?;
+    let (not_full, r) = read_bool(r)?;
+    let (game_version, r) = read_string(r)?;
+
+    Ok((RoomFilter { properties, not_full, game_version }, r))
+}
+
+pub fn push_room_filter(buf: &mut Vec<u8>, filter: &RoomFilter) {
+    push_string_pairs(buf, &filter.properties);
+    push_bool(buf, filter.not_full);
+    push_string(buf, &filter.game_version);
+}
+
+pub fn read_room_sort(bytes: &[u8]) -> Result<(RoomSort, &[u8]), ProtocolError> {
+    let (value, r) = read_i32(bytes)?;
+    let sort = RoomSort::from_i32(value).ok_or(ProtocolError::InvalidRoomSort(value))?;
+    Ok((sort, r))
+}
@@ -1,23 +1,56 @@
-use crate::protocol::packet::PacketType;
+use std::collections::{HashMap, HashSet};
+use crate::protocol::packet::{GameDataTarget, InviteCode, PacketType, RoomFilter, RoomInfo, RoomSort};
 use crate::relay_client::events::RelayEvent;
 use std::cmp::PartialEq;
+use std::net::SocketAddr;
 use std::time::Duration;
-use crate::protocol::version;
+use crate::protocol::{error_codes, version};
 use crate::relay_client::error::RelayClientError;
-use crate::transport::client::{ClientEvent, ClientTransport};
+use crate::transport::client::{max_unreliable_payload, ClientEvent, ClientTransport, UnackedMessage, DEFAULT_CHANNEL_COUNT};
 use crate::transport::common::{Channel};
+use crate::transport::stats::NetworkStats;
+
+const DISCONNECT_FLUSH_TIMEOUT: Duration = Duration::from_millis(250);
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(8);
+pub const DEFAULT_MAX_RECONNECT_ATTEMPTS: u32 = 5;
+const MAX_PENDING_GAME_DATA: usize = 256;
 
 #[derive(Debug, PartialEq)]
 enum ClientState {
     Connecting,
     Connected,
     Authenticated,
+    Joining,
+    InRoom,
+    Reconnecting,
+}
+
+struct Reconnect {
+    attempt: u32,
+    retry_in: Duration,
+    buffered: Vec<(GameDataTarget, Vec<u8>, u8)>,
+    unacked: Vec<UnackedMessage>,
+    overflowed: bool,
 }
 
 pub struct RelayClient {
     transport: Option<ClientTransport>,
     client_state: ClientState,
     last_update: Duration,
+    server_addr: Option<SocketAddr>,
+    timeout: Duration,
+    channel_count: usize,
+    app_id: String,
+    game_version: String,
+    resume_token: Option<String>,
+    auto_reconnect: bool,
+    max_reconnect_attempts: u32,
+    reconnect: Option<Reconnect>,
+    room_cache: HashMap<String, RoomInfo>,
+    known_peers: HashSet<i32>,
+    pending_game_data: HashMap<i32, Vec<RelayEvent>>,
 }
 
 impl RelayClient {
@@ -26,22 +59,56 @@ impl RelayClient {
             transport: None,
             client_state: ClientState::Connecting,
             last_update: Duration::from_secs(0),
+            server_addr: None,
+            timeout: Duration::ZERO,
+            channel_count: DEFAULT_CHANNEL_COUNT,
+            app_id: String::new(),
+            game_version: String::new(),
+            resume_token: None,
+            auto_reconnect: true,
+            max_reconnect_attempts: DEFAULT_MAX_RECONNECT_ATTEMPTS,
+            reconnect: None,
+            room_cache: HashMap::new(),
+            known_peers: HashSet::new(),
+            pending_game_data: HashMap::new(),
         }
     }
 
     pub fn connect(&mut self, transport: ClientTransport) {
         self.client_state = ClientState::Connecting;
+        self.server_addr = Some(transport.server_addr());
+        self.timeout = transport.timeout();
+        self.channel_count = transport.channel_count();
+        self.resume_token = None;
+        self.reconnect = None;
+        self.room_cache.clear();
+        self.clear_room_peers();
         self.transport = Some(transport);
     }
 
+    pub fn set_reconnect_policy(&mut self, enabled: bool, max_attempts: u32) {
+        self.auto_reconnect = enabled;
+        self.max_reconnect_attempts = max_attempts;
+    }
+
     pub fn update(&mut self, delta: Duration) -> Result<Vec<RelayEvent>, RelayClientError> {
+        if self.reconnect.as_ref().is_some_and(|r| r.overflowed) {
+            self.reset_session();
+            return Ok(vec![RelayEvent::Disconnected { reason: RelayClientError::ReconnectBufferFull.to_string() }]);
+        }
+
+        if self.client_state == ClientState::Reconnecting {
+            return Ok(self.update_reconnect(delta));
+        }
+
         let transport = self.transport.as_mut().ok_or(
             RelayClientError::TransportNotInitialized
         )?;
 
         self.last_update += delta;
-        if self.last_update >= Duration::from_secs(5) {
-            transport.send_keepalive().expect("TODO: panic message");
+        if self.last_update >= PING_INTERVAL && transport.is_connected() {
+            // A failed ping is not fatal; the liveness timeout covers a dead link.
+            let _ = transport.send_ping();
             self.last_update = Duration::ZERO;
         }
 
@@ -54,9 +121,22 @@ impl RelayClient {
         }
 
         for event in events {
-            if let ClientEvent::PacketReceived { data, channel } = event {
-                let packet_events = self.handle_packet(data, channel)?;
-                relay_events.extend(packet_events);
+            match event {
+                ClientEvent::PacketReceived { data, channel } => {
+                    let packet_events = self.handle_packet(data, channel)?;
+                    relay_events.extend(packet_events);
+                }
+                ClientEvent::Disconnected { reason } => {
+                    let unacked = self.transport.take()
+                        .map(|mut transport| transport.take_unacked_game_data())
+                        .unwrap_or_default();
+
+                    relay_events.push(self.connection_lost(reason));
+                    if let Some(reconnect) = self.reconnect.as_mut() {
+                        reconnect.unacked.extend(unacked);
+                    }
+                    break;
+                }
             }
         }
 
@@ -66,36 +146,255 @@ impl RelayClient {
     fn update_state(&mut self) -> Option<RelayEvent> {
         if self.client_state == ClientState::Connecting && self.is_connected() {
             self.client_state = ClientState::Connected;
+
+            // While resuming we authenticate on our own and only report
+            // back once the relay has restored the session.
+            if self.reconnect.is_some() {
+                if let Err(e) = self.send_auth() {
+                    return Some(self.connection_lost(e.to_string()));
+                }
+                return None;
+            }
+
             return Some(RelayEvent::ConnectedToServer);
         }
 
         None
     }
 
+    fn connection_lost(&mut self, reason: String) -> RelayEvent {
+        let resumable = match self.reconnect {
+            Some(_) => true,
+            None => self.auto_reconnect
+                && self.resume_token.is_some()
+                && matches!(self.client_state, ClientState::Authenticated | ClientState::Joining | ClientState::InRoom),
+        };
+
+        let attempt = self.reconnect.as_ref().map_or(1, |r| r.attempt + 1);
+
+        if !resumable || attempt > self.max_reconnect_attempts {
+            self.reset_session();
+            return RelayEvent::Disconnected { reason };
+        }
+
+        let (buffered, unacked) = self.reconnect.take()
+            .map(|r| (r.buffered, r.unacked))
+            .unwrap_or_default();
+        let retry_in = RECONNECT_BASE_DELAY
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(RECONNECT_MAX_DELAY);
+
+        self.client_state = ClientState::Reconnecting;
+        self.reconnect = Some(Reconnect { attempt, retry_in, buffered, unacked, overflowed: false });
+
+        RelayEvent::Reconnecting { attempt }
+    }
+
+    fn update_reconnect(&mut self, delta: Duration) -> Vec<RelayEvent> {
+        let (Some(reconnect), Some(server_addr)) = (self.reconnect.as_mut(), self.server_addr) else {
+            self.reset_session();
+            return vec![RelayEvent::Disconnected { reason: "Reconnect state lost".to_string() }];
+        };
+
+        reconnect.retry_in = reconnect.retry_in.saturating_sub(delta);
+        if !reconnect.retry_in.is_zero() {
+            return vec![];
+        }
+
+        match ClientTransport::new(server_addr) {
+            Ok(mut transport) => {
+                transport.set_timeout(self.timeout);
+                transport.set_channel_count(self.channel_count);
+                self.transport = Some(transport);
+                self.client_state = ClientState::Connecting;
+                self.last_update = Duration::ZERO;
+                vec![]
+            }
+            Err(e) => vec![self.connection_lost(e.to_string())],
+        }
+    }
+
+    fn reset_session(&mut self) {
+        self.transport = None;
+        self.client_state = ClientState::Connecting;
+        self.resume_token = None;
+        self.reconnect = None;
+        self.room_cache.clear();
+        self.clear_room_peers();
+    }
+
+    fn clear_room_peers(&mut self) {
+        self.known_peers.clear();
+        self.pending_game_data.clear();
+    }
+
+    fn abandon_join(&mut self) {
+        if self.client_state == ClientState::Joining {
+            self.client_state = ClientState::Authenticated;
+            self.clear_room_peers();
+        }
+    }
+
+    // Membership events travel on the control lane and game data on its own
+    // lanes, so data from a peer can overtake the event announcing it. Such
+    // data is held back until the peer is known. The relay announces every
+    // member already in the room to a joiner with PeerJoinedRoom, and the
+    // host (peer 1) implicitly with ConnectedToRoom.
+    fn accept_peer(&mut self, peer_id: i32, events: &mut Vec<RelayEvent>) {
+        self.known_peers.insert(peer_id);
+        if let Some(pending) = self.pending_game_data.remove(&peer_id) {
+            events.extend(pending);
+        }
+    }
+
     fn handle_packet(&mut self, data: Vec<u8>, channel: Channel) -> Result<Vec<RelayEvent>, RelayClientError> {
         let mut events = vec![];
 
         if let Ok(packet_type) = PacketType::from_bytes(&data) {
             match packet_type {
-                PacketType::ClientAuthenticated => {
+                // A fresh session in reply to a resume means the old one is gone.
+                PacketType::ClientAuthenticated { .. } if self.reconnect.is_some() => {
+                    self.reset_session();
+                    events.push(RelayEvent::Disconnected { reason: "Relay did not resume the session".to_string() });
+                }
+                PacketType::ClientAuthenticated { resume_token } => {
                     self.client_state = ClientState::Authenticated;
+                    self.resume_token = Some(resume_token);
                     events.push(RelayEvent::Authenticated);
                 }
-                PacketType::ConnectedToRoom { room_id, peer_id } =>
-                    events.push(RelayEvent::RoomJoined { room_id, peer_id }),
-                PacketType::GetRooms { rooms } =>
-                    events.push(RelayEvent::RoomsReceived { rooms }),
+                PacketType::ConnectedToRoom { room_id, peer_id, resume_token, invite_code } => {
+                    self.client_state = ClientState::InRoom;
+                    self.resume_token = Some(resume_token);
+                    events.push(RelayEvent::RoomJoined { room_id, peer_id, invite_code });
+
+                    self.accept_peer(1, &mut events);
+                    self.known_peers.insert(peer_id);
+                }
+                PacketType::SessionResumed { room_id, peer_id, resume_token, received_seqs } => {
+                    self.client_state = if room_id.is_empty() {
+                        self.clear_room_peers();
+                        ClientState::Authenticated
+                    } else {
+                        ClientState::InRoom
+                    };
+                    self.resume_token = Some(resume_token);
+
+                    // Whatever the relay did receive before the drop is not
+                    // sent again, so nothing is delivered twice.
+                    if let Some(reconnect) = self.reconnect.take()
+                        && !room_id.is_empty()
+                    {
+                        let unacked = reconnect.unacked.into_iter()
+                            .filter(|message| !message.was_received(&received_seqs))
+                            .filter_map(|message| match PacketType::from_bytes(&message.data) {
+                                Ok(PacketType::SendGameData { target, transfer_channel, data }) =>
+                                    Some((target, data, transfer_channel as u8)),
+                                _ => None,
+                            });
+
+                        for (target, data, transfer_channel) in unacked.chain(reconnect.buffered) {
+                            self.send_game_data(target, data, Channel::Reliable, transfer_channel)?;
+                        }
+                    }
+
+                    events.push(RelayEvent::Reconnected { room_id, peer_id });
+                }
+                PacketType::Error { error_code: error_codes::RESUME_FAILED, error_message } => {
+                    self.reset_session();
+                    events.push(RelayEvent::Disconnected { reason: error_message });
+                }
+                PacketType::GetRooms { rooms, next_cursor } =>
+                    events.push(RelayEvent::RoomsReceived { rooms, next_cursor }),
                 PacketType::PeerJoinAttempt { target_id, metadata } =>
                     events.push(RelayEvent::PeerJoinAttempt { client_id: target_id, metadata } ),
-                PacketType::PeerJoinedRoom { peer_id } =>
-                    events.push(RelayEvent::PeerJoinedRoom { peer_id }),
-                PacketType::PeerLeftRoom { peer_id } =>
-                    events.push(RelayEvent::PeerLeftRoom { peer_id }),
-                PacketType::GameData { from_peer, data } => {
-                    events.push(RelayEvent::GameDataReceived { data, from_peer, channel });
+                // The relay forwards the host's response to the joining
+                // client; accepted joins are followed by ConnectedToRoom.
+                PacketType::JoinRes { allowed: false, reason, message, .. } => {
+                    self.abandon_join();
+                    events.push(RelayEvent::JoinRejected { reason, message });
+                }
+                PacketType::JoinRes { .. } => {}
+                PacketType::PeerJoinedRoom { peer_id, metadata } => {
+                    events.push(RelayEvent::PeerJoinedRoom { peer_id, metadata });
+                    self.accept_peer(peer_id, &mut events);
+                }
+                PacketType::PeerLeftRoom { peer_id } => {
+                    self.known_peers.remove(&peer_id);
+                    self.pending_game_data.remove(&peer_id);
+                    events.push(RelayEvent::PeerLeftRoom { peer_id });
+                }
+                PacketType::GameData { from_peer, transfer_channel, data } => {
+                    let event = RelayEvent::GameDataReceived { data, transfer_channel, from_peer, channel };
+
+                    match self.client_state {
+                        ClientState::InRoom if self.known_peers.contains(&from_peer) => events.push(event),
+                        ClientState::InRoom | ClientState::Joining => {
+                            let pending = self.pending_game_data.entry(from_peer).or_default();
+                            if pending.len() < MAX_PENDING_GAME_DATA {
+                                pending.push(event);
+                            }
+                        }
+                        // Data from a room we already left can still be in flight.
+                        _ => {}
+                    }
                 }
                 PacketType::ForceDisconnect =>
                     events.push(RelayEvent::ForceDisconnect),
+                PacketType::HostChanged { new_host_id } => {
+                    self.known_peers.remove(&new_host_id);
+                    let pending = self.pending_game_data.remove(&new_host_id);
+                    events.push(RelayEvent::HostChanged { new_host_id });
+
+                    if let Some(pending) = pending {
+                        self.pending_game_data.entry(1).or_default().extend(pending);
+                    }
+                    self.accept_peer(1, &mut events);
+                }
+                PacketType::KickPeer { reason, .. } => {
+                    self.client_state = ClientState::Authenticated;
+                    self.clear_room_peers();
+                    events.push(RelayEvent::Kicked { reason });
+                }
+                PacketType::Error { error_code: error_codes::ROOM_FULL, .. } => {
+                    self.abandon_join();
+                    events.push(RelayEvent::RoomFull);
+                }
+                PacketType::RoomListAdded { room } | PacketType::RoomListUpdated { room } => {
+                    self.room_cache.insert(room.id.clone(), room.clone());
+                    events.push(RelayEvent::RoomListChanged { room_id: room.id.clone(), room: Some(room) });
+                }
+                PacketType::RoomListRemoved { room_id } => {
+                    self.room_cache.remove(&room_id);
+                    events.push(RelayEvent::RoomListChanged { room_id, room: None });
+                }
+                PacketType::RoomUpdated { room_id, metadata } =>
+                    events.push(RelayEvent::RoomUpdated { room_id, metadata }),
+                // Like game data, changes from a room we already left can still
+                // be in flight.
+                PacketType::RoomPropertyChanged { key, value } => {
+                    if self.client_state == ClientState::InRoom {
+                        events.push(RelayEvent::RoomPropertyChanged { key, value });
+                    }
+                }
+                // The relay reports the rejected key as the error message.
+                PacketType::Error { error_code: error_codes::PROPERTY_CONFLICT, error_message } =>
+                    events.push(RelayEvent::RoomPropertyConflict { key: error_message }),
+                PacketType::Error { error_code: error_codes::VERSION_MISMATCH, .. } => {
+                    self.abandon_join();
+                    events.push(RelayEvent::VersionMismatch);
+                }
+                PacketType::Error { error_code: error_codes::ROOM_LOCKED, .. } => {
+                    self.abandon_join();
+                    events.push(RelayEvent::RoomLocked);
+                }
+                PacketType::Error { error_code: error_codes::ROOM_CODE_TAKEN, .. } => {
+                    self.abandon_join();
+                    events.push(RelayEvent::RoomCodeTaken);
+                }
+                PacketType::Error { error_code: error_codes::WRONG_PASSWORD, .. } => {
+                    self.abandon_join();
+                    events.push(RelayEvent::WrongPassword);
+                }
                 PacketType::Error { error_code, error_message } =>
                     events.push(RelayEvent::Error { error_code, error_message }),
                 _ => {
@@ -109,11 +408,20 @@ impl RelayClient {
         Ok(events)
     }
 
-    pub fn req_auth(&mut self, app_id: String) -> Result<(), RelayClientError> {
-        self.send_packet(
+    pub fn req_auth(&mut self, app_id: String, game_version: String) -> Result<(), RelayClientError> {
+        self.app_id = app_id;
+        self.game_version = game_version;
+        self.send_auth()
+    }
+
+    fn send_auth(&mut self) -> Result<(), RelayClientError> {
+        self.send_raw(
             PacketType::Authenticate {
-                app_id,
-                version: version::PROTOCOL_VERSION.to_string()
+                app_id: self.app_id.clone(),
+                version: version::PROTOCOL_VERSION.to_string(),
+                resume_token: self.resume_token.clone().unwrap_or_default(),
+                game_version: self.game_version.clone(),
+                channel_count: self.channel_count as i32,
             },
             Channel::Reliable
         )?;
@@ -121,31 +429,75 @@ impl RelayClient {
         Ok(())
     }
 
-    pub fn req_create_room(&mut self, is_public: bool, metadata: String) -> Result<(), RelayClientError> {
+    pub fn req_create_room(
+        &mut self,
+        is_public: bool,
+        metadata: String,
+        max_players: i32,
+        password: String,
+        host_name: String,
+        invite_code: InviteCode
+    ) -> Result<(), RelayClientError> {
+        self.ensure_out_of_room()?;
+
         self.send_packet(
             PacketType::CreateRoom {
                 is_public,
                 metadata,
+                max_players,
+                password,
+                host_name,
+                invite_code,
             },
             Channel::Reliable
         )?;
 
+        self.clear_room_peers();
+        self.client_state = ClientState::Joining;
+
         Ok(())
     }
 
-    pub fn req_rooms(&mut self) -> Result<(), RelayClientError> {
+    pub fn req_rooms(&mut self, filter: RoomFilter, sort: RoomSort, cursor: String, limit: i32) -> Result<(), RelayClientError> {
         self.send_packet(
-            PacketType::ReqRooms,
+            PacketType::ReqRooms { filter, sort, cursor, limit },
             Channel::Reliable,
         )
     }
 
-    pub fn req_join_room(&mut self, room_id: String, metadata: String) -> Result<(), RelayClientError> {
+    pub fn subscribe_rooms(&mut self, filter: RoomFilter) -> Result<(), RelayClientError> {
+        self.room_cache.clear();
+
+        self.send_packet(
+            PacketType::SubscribeRooms { filter },
+            Channel::Reliable,
+        )
+    }
+
+    pub fn unsubscribe_rooms(&mut self) -> Result<(), RelayClientError> {
+        self.room_cache.clear();
+
+        self.send_packet(
+            PacketType::UnsubscribeRooms,
+            Channel::Reliable,
+        )
+    }
+
+    pub fn cached_rooms(&self) -> impl Iterator<Item = &RoomInfo> {
+        self.room_cache.values()
+    }
+
+    pub fn req_join_room(&mut self, room_id: String, metadata: String, password: String) -> Result<(), RelayClientError> {
+        self.ensure_out_of_room()?;
+
         self.send_packet(
-            PacketType::ReqJoin { room_id, metadata },
+            PacketType::ReqJoin { room_id, metadata, password },
             Channel::Reliable
         )?;
 
+        self.clear_room_peers();
+        self.client_state = ClientState::Joining;
+
         Ok(())
     }
 
@@ -158,12 +510,16 @@ impl RelayClient {
         Ok(())
     }
 
-    pub fn send_join_response(&mut self, room_id: String, target_id: u64, allowed: bool) -> Result<(), RelayClientError> {
+    pub fn send_join_response(&mut self, room_id: String, target_id: u64, allowed: bool, message: String) -> Result<(), RelayClientError> {
+        let reason = if allowed { 0 } else { error_codes::JOIN_REJECTED };
+
         self.send_packet(
             PacketType::JoinRes {
                 allowed,
                 room_id,
-                target_id
+                target_id,
+                reason,
+                message
             },
             Channel::Reliable
         )?;
@@ -171,20 +527,164 @@ impl RelayClient {
         Ok(())
     }
 
-    pub fn send_game_data(&mut self, peer_id: i32, data: Vec<u8>, channel: Channel) -> Result<(), RelayClientError> {
+    pub fn send_kick(&mut self, peer_id: i32, reason: String) -> Result<(), RelayClientError> {
+        self.send_packet(
+            PacketType::KickPeer { peer_id, reason },
+            Channel::Reliable
+        )?;
+
+        Ok(())
+    }
+
+    pub fn send_host_successor(&mut self, peer_id: i32) -> Result<(), RelayClientError> {
+        self.send_packet(
+            PacketType::SetHostSuccessor { peer_id },
+            Channel::Reliable
+        )?;
+
+        Ok(())
+    }
+
+    pub fn send_room_locked(&mut self, room_id: String, locked: bool) -> Result<(), RelayClientError> {
         self.send_packet(
-            PacketType::GameData { from_peer: peer_id, data },
-            channel
+            PacketType::SetRoomLocked { room_id, locked },
+            Channel::Reliable
+        )?;
+
+        Ok(())
+    }
+
+    pub fn req_set_room_property(
+        &mut self,
+        key: String,
+        value: Option<String>,
+        expected: Option<String>,
+        listed: bool
+    ) -> Result<(), RelayClientError> {
+        if self.client_state != ClientState::InRoom {
+            return Err(RelayClientError::NotInRoom);
+        }
+
+        self.send_packet(
+            PacketType::SetRoomProperty { key, value, expected, listed },
+            Channel::Reliable
+        )?;
+
+        Ok(())
+    }
+
+    pub fn leave_room(&mut self) -> Result<(), RelayClientError> {
+        if self.client_state != ClientState::InRoom {
+            return Err(RelayClientError::NotInRoom);
+        }
+
+        self.send_packet(
+            PacketType::LeaveRoom,
+            Channel::Reliable
+        )?;
+
+        self.client_state = ClientState::Authenticated;
+        self.clear_room_peers();
+
+        Ok(())
+    }
+
+    /// Tells the relay we are leaving, waits briefly for it to acknowledge
+    /// everything still in flight, then drops the transport.
+    pub fn disconnect(&mut self) -> Result<(), RelayClientError> {
+        let result = match self.transport {
+            Some(_) => self.send_raw(PacketType::Disconnect, Channel::Reliable),
+            None => Ok(()),
+        };
+
+        if let Some(mut transport) = self.transport.take() {
+            transport.flush(DISCONNECT_FLUSH_TIMEOUT);
+        }
+
+        self.reset_session();
+        self.last_update = Duration::ZERO;
+
+        result
+    }
+
+    pub fn send_game_data(&mut self, target: GameDataTarget, data: Vec<u8>, channel: Channel, transfer_channel: u8) -> Result<(), RelayClientError> {
+        // Reliable data is held back while reconnecting and replayed once the
+        // session is resumed; unreliable data would be stale by then anyway.
+        if let Some(reconnect) = self.reconnect.as_mut() {
+            if channel != Channel::Reliable {
+                return Ok(());
+            }
+
+            if reconnect.buffered.len() >= MAX_PENDING_GAME_DATA {
+                reconnect.overflowed = true;
+                return Err(RelayClientError::ReconnectBufferFull);
+            }
+
+            reconnect.buffered.push((target, data, transfer_channel));
+            return Ok(());
+        }
+
+        let transport = self.transport.as_mut().ok_or(
+            RelayClientError::TransportNotInitialized
+        )?;
+
+        transport.send_game(
+            PacketType::SendGameData { target, transfer_channel: transfer_channel as i32, data }.to_bytes(),
+            channel,
+            transfer_channel,
         )?;
 
         Ok(())
     }
 
+    fn ensure_out_of_room(&self) -> Result<(), RelayClientError> {
+        match self.client_state {
+            ClientState::Authenticated | ClientState::Joining => Ok(()),
+            ClientState::InRoom => Err(RelayClientError::AlreadyInRoom),
+            _ => Err(RelayClientError::NotAuthenticated),
+        }
+    }
+
+    pub fn max_unreliable_game_data_size(channel: Channel) -> usize {
+        let header = PacketType::SendGameData {
+            target: GameDataTarget::Broadcast,
+            transfer_channel: 0,
+            data: vec![],
+        }.to_bytes().len();
+        max_unreliable_payload(channel) - header
+    }
+
+    /// The number of transfer channels the relay was told about when
+    /// connecting.
+    pub fn channel_count(&self) -> usize {
+        self.channel_count
+    }
+
+    pub fn stats(&self) -> NetworkStats {
+        self.transport.as_ref().map(|transport| transport.stats()).unwrap_or_default()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.transport.is_some() || self.reconnect.is_some()
+    }
+
+    pub fn is_in_room(&self) -> bool {
+        self.client_state == ClientState::InRoom
+    }
+
     pub fn is_connected(&self) -> bool {
         self.transport.as_ref().map_or(false, |transport| transport.is_connected())
     }
 
     fn send_packet(&mut self, packet_type: PacketType, channel: Channel) -> Result<(), RelayClientError> {
+        if self.reconnect.is_some() {
+            return Err(RelayClientError::Reconnecting);
+        }
+
+        self.send_raw(packet_type, channel)
+    }
+
+    fn send_raw(&mut self, packet_type: PacketType, channel: Channel) -> Result<(), RelayClientError> {
         let transport = self.transport.as_mut().ok_or(
             RelayClientError::TransportNotInitialized
         )?;
@@ -192,7 +692,7 @@ impl RelayClient {
         transport.send(
             packet_type.to_bytes(),
             channel,
-        ).expect("TODO: panic message");
+        )?;
 
         Ok(())
     }
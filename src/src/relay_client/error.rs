@@ -14,4 +14,19 @@ pub enum RelayClientError {
 
     #[error("Packet parsing error")]
     PacketParsingError,
+
+    #[error("Not authenticated with the relay")]
+    NotAuthenticated,
+
+    #[error("Already in a room")]
+    AlreadyInRoom,
+
+    #[error("Not in a room")]
+    NotInRoom,
+
+    #[error("Reconnecting to the relay")]
+    Reconnecting,
+
+    #[error("Too much game data queued while reconnecting")]
+    ReconnectBufferFull,
 }
\ No newline at end of file
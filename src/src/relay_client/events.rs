@@ -5,12 +5,27 @@ use crate::transport::common::Channel;
 pub enum RelayEvent {
     ConnectedToServer,
     Authenticated,
-    RoomsReceived { rooms: Vec<RoomInfo> },
-    RoomJoined { room_id: String, peer_id: i32 },
+    RoomsReceived { rooms: Vec<RoomInfo>, next_cursor: String },
+    RoomJoined { room_id: String, peer_id: i32, invite_code: String },
+    RoomUpdated { room_id: String, metadata: String },
     PeerJoinAttempt { client_id: u64, metadata: String },
-    PeerJoinedRoom { peer_id: i32 },
-    GameDataReceived { channel: Channel, from_peer: i32, data: Vec<u8> },
+    PeerJoinedRoom { peer_id: i32, metadata: String },
+    GameDataReceived { channel: Channel, transfer_channel: i32, from_peer: i32, data: Vec<u8> },
     PeerLeftRoom { peer_id: i32 },
     ForceDisconnect,
+    Disconnected { reason: String },
+    Reconnecting { attempt: u32 },
+    Reconnected { room_id: String, peer_id: i32 },
+    RoomFull,
+    WrongPassword,
+    RoomCodeTaken,
+    RoomLocked,
+    VersionMismatch,
+    JoinRejected { reason: i32, message: String },
+    Kicked { reason: String },
+    HostChanged { new_host_id: i32 },
+    RoomPropertyChanged { key: String, value: Option<String> },
+    RoomPropertyConflict { key: String },
+    RoomListChanged { room_id: String, room: Option<RoomInfo> },
     Error { error_code: i32, error_message: String },
 }
\ No newline at end of file
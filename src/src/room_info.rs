@@ -0,0 +1,68 @@
+use godot::builtin::{Dictionary, GString};
+use godot::classes::{IRefCounted, RefCounted};
+use godot::obj::{Base, Gd};
+use godot::prelude::{godot_api, GodotClass};
+use crate::protocol::packet::RoomInfo;
+
+/// A room as listed by the relay.
+#[derive(GodotClass)]
+#[class(no_init, base=RefCounted)]
+pub struct NodeTunnelRoomInfo {
+    #[var(get)]
+    id: GString,
+    #[var(get)]
+    metadata: GString,
+    #[var(get)]
+    player_count: i32,
+    #[var(get)]
+    max_players: i32,
+    #[var(get)]
+    properties: Dictionary,
+    #[var(get)]
+    host_name: GString,
+    /// Unix timestamp in seconds.
+    #[var(get)]
+    created_at: i64,
+    #[var(get)]
+    region: GString,
+    #[var(get)]
+    has_password: bool,
+    /// The relay's estimate of the latency to the host, 0 when unknown.
+    #[var(get)]
+    latency_hint_ms: i32,
+    base: Base<RefCounted>
+}
+
+#[godot_api]
+impl IRefCounted for NodeTunnelRoomInfo {}
+
+#[godot_api]
+impl NodeTunnelRoomInfo {
+    #[func]
+    fn is_full(&self) -> bool {
+        self.max_players > 0 && self.player_count >= self.max_players
+    }
+}
+
+impl NodeTunnelRoomInfo {
+    pub fn from_room_info(room: &RoomInfo) -> Gd<Self> {
+        let mut properties = Dictionary::new();
+        for (key, value) in &room.properties {
+            properties.set(key.clone(), value.clone());
+        }
+
+        Gd::from_init_fn(|base| Self {
+            id: room.id.as_str().into(),
+            metadata: room.metadata.as_str().into(),
+            player_count: room.player_count,
+            max_players: room.max_players,
+            properties,
+            host_name: room.host_name.as_str().into(),
+            created_at: room.created_at as i64,
+            region: room.region.as_str().into(),
+            has_password: room.has_password,
+            latency_hint_ms: room.latency_hint_ms,
+            base,
+        })
+    }
+}
@@ -1,23 +1,64 @@
+use std::collections::VecDeque;
 use std::io::ErrorKind;
 use std::net::{SocketAddr, UdpSocket};
 use std::time::{Duration, Instant};
 use paperudp::channel::DecodeResult;
-use paperudp::packet::PacketType;
 use crate::transport::common::Channel;
+use crate::transport::control;
+use crate::transport::error::TransportError;
+use crate::transport::lane::{Lane, SEQUENCE_HEADER_SIZE};
+use crate::transport::reliable::seq_before;
+use crate::transport::stats::NetworkStats;
+
+const MIN_RESEND_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+const MAX_UNRELIABLE_PAYLOAD: usize = 1200;
+const MAX_RELIABLE_PAYLOAD: usize = 1200;
+const HANDSHAKE_INTERVAL: Duration = Duration::from_millis(250);
+const CONTROL_LANE: usize = 0;
+pub const DEFAULT_CHANNEL_COUNT: usize = 4;
+const MAX_CHANNEL_COUNT: usize = u8::MAX as usize;
+const PING_LOSS_TIMEOUT: Duration = Duration::from_secs(2);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct ClientTransport {
     socket: UdpSocket,
-    channel: paperudp::channel::Channel,
+    lanes: Vec<Lane>,
+    channel_count: usize,
     server_addr: SocketAddr,
     pending_events: Vec<ClientEvent>,
     pending_sends: Vec<Vec<u8>>,
     last_resend_check: Instant,
+    last_handshake: Instant,
+    last_recv: Instant,
+    timeout: Duration,
     connected: bool,
+    timed_out: bool,
+    started: Instant,
+    next_ping_seq: u32,
+    outstanding_pings: VecDeque<(u32, Instant)>,
+    stats: NetworkStats,
+}
+
+/// A reliable game message the relay had not acknowledged when the
+/// connection dropped.
+pub struct UnackedMessage {
+    lane_id: usize,
+    last_seq: u32,
+    pub data: Vec<u8>,
+}
+
+impl UnackedMessage {
+    /// Whether the relay got it after all, going by the first sequence
+    /// number it reported missing on each lane.
+    pub fn was_received(&self, received_seqs: &[u32]) -> bool {
+        received_seqs.get(self.lane_id).is_some_and(|next| seq_before(self.last_seq, *next))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum ClientEvent {
     PacketReceived { data: Vec<u8>, channel: Channel },
+    Disconnected { reason: String },
 }
 
 impl ClientTransport {
@@ -25,55 +66,171 @@ impl ClientTransport {
         let socket = UdpSocket::bind("0.0.0.0:0")?;
         socket.set_nonblocking(true)?;
 
-        Ok(Self {
+        let now = Instant::now();
+        let mut transport = Self {
             socket,
             server_addr,
-            channel: paperudp::channel::Channel::new(),
+            lanes: vec![Lane::new()],
+            channel_count: DEFAULT_CHANNEL_COUNT,
             pending_events: Vec::new(),
             pending_sends: Vec::new(),
-            last_resend_check: Instant::now(),
-            connected: true,
-        })
+            last_resend_check: now,
+            last_handshake: now,
+            last_recv: now,
+            timeout: DEFAULT_TIMEOUT,
+            connected: false,
+            timed_out: false,
+            started: now,
+            next_ping_seq: 0,
+            outstanding_pings: VecDeque::new(),
+            stats: NetworkStats::default(),
+        };
+
+        transport.send_handshake()?;
+
+        Ok(transport)
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sets how many game transfer channels may be used. Each one gets its
+    /// own lane on the wire, after the lane reserved for relay control.
+    pub fn set_channel_count(&mut self, count: usize) {
+        self.channel_count = count.clamp(1, MAX_CHANNEL_COUNT - 1);
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.channel_count
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn server_addr(&self) -> SocketAddr {
+        self.server_addr
+    }
+
+    pub fn stats(&self) -> NetworkStats {
+        self.stats
     }
 
     pub fn recv_packets(&mut self) -> Vec<ClientEvent> {
-        let mut buf = [0u8; 65535];
         let now = Instant::now();
 
         self.flush_pending_packets();
 
-        if now.duration_since(self.last_resend_check) > Duration::from_millis(50) {
+        let resend_check_interval = (self.stats.rto() / 2).max(MIN_RESEND_CHECK_INTERVAL);
+        if now.duration_since(self.last_resend_check) > resend_check_interval {
             self.do_resends();
             self.last_resend_check = now;
         }
 
+        if !self.connected && !self.timed_out && now.duration_since(self.last_handshake) > HANDSHAKE_INTERVAL {
+            let _ = self.send_handshake();
+        }
+
+        self.read_socket();
+        self.expire_pings();
+        self.check_timeout();
+
+        std::mem::take(&mut self.pending_events)
+    }
+
+    fn check_timeout(&mut self) {
+        if self.timed_out || self.last_recv.elapsed() <= self.timeout {
+            return;
+        }
+
+        let reason = if self.connected {
+            "Relay stopped responding"
+        } else {
+            "Timed out waiting for relay handshake"
+        };
+
+        self.timed_out = true;
+        self.connected = false;
+        self.pending_events.push(ClientEvent::Disconnected { reason: reason.to_string() });
+    }
+
+    /// Blocks until every reliable packet sent so far has been acknowledged,
+    /// or until `timeout` elapses. Used before dropping the transport so that
+    /// goodbye packets actually reach the relay.
+    pub fn flush(&mut self, timeout: Duration) {
+        let started = Instant::now();
+
+        while started.elapsed() < timeout {
+            self.flush_pending_packets();
+            self.read_socket();
+
+            if self.pending_sends.is_empty() && !self.lanes.iter().any(Lane::has_unacked) {
+                break;
+            }
+
+            self.do_resends();
+            if self.timed_out {
+                return;
+            }
+
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn read_socket(&mut self) {
+        let mut buf = [0u8; 65535];
+
         loop {
             match self.socket.recv_from(&mut buf) {
-                Ok((len, _addr)) => {
-                    if len == 0 { continue; }
-                    let res = self.channel.decode(&buf[..len]);
+                Ok((len, addr)) => {
+                    if len < 2 || addr != self.server_addr { continue; }
+                    self.last_recv = Instant::now();
+                    self.stats.record_received(len);
+
+                    // The channel count only limits what we send; peers may
+                    // be configured with more channels than we are.
+                    let lane_id = buf[0] as usize;
+                    let res = self.lane(lane_id).channel.decode(&buf[1..len]);
 
                     match res {
                         DecodeResult::Unreliable { payload } => {
                             for p in payload {
+                                if lane_id == CONTROL_LANE && self.handle_control(&p) { continue; }
+
+                                let (data, channel) = match p.first() {
+                                    Some(&control::RELIABLE) => {
+                                        let (messages, ack) = self.lanes[lane_id].receive_reliable(&p);
+                                        let _ = self.try_send_packet(frame(lane_id, ack));
+
+                                        for data in messages {
+                                            self.pending_events.push(ClientEvent::PacketReceived {
+                                                data,
+                                                channel: Channel::Reliable,
+                                            });
+                                        }
+                                        continue;
+                                    }
+                                    Some(&control::ACK) => {
+                                        self.lanes[lane_id].receive_ack(&p);
+                                        continue;
+                                    }
+                                    Some(&control::SEQUENCED) => match self.lanes[lane_id].accept_sequenced(&p) {
+                                        Some(data) => (data, Channel::UnreliableOrdered),
+                                        None => continue,
+                                    },
+                                    _ => (p, Channel::Unreliable),
+                                };
+
                                 self.pending_events.push(ClientEvent::PacketReceived {
-                                    data: p,
-                                    channel: Channel::Unreliable,
-                                });
-                            }
-                        }
-                        DecodeResult::Reliable { payload, ack_packet, .. } => {
-                            for p in payload {
-                                self.pending_events.push(ClientEvent::PacketReceived {
-                                    data: p,
-                                    channel: Channel::Reliable,
+                                    data,
+                                    channel,
                                 });
                             }
-
-                            if let Some(ack) = ack_packet {
-                                self.socket.send_to(&ack, self.server_addr).unwrap();
-                            }
                         }
+                        // Reliable delivery is handled per lane, on top of
+                        // paperudp's unreliable packets.
+                        DecodeResult::Reliable { .. } => {}
                         DecodeResult::Ack { .. } => {}
                         DecodeResult::None => {}
                     }
@@ -82,35 +239,72 @@ impl ClientTransport {
                 Err(_) => break,
             }
         }
+    }
+
+    pub fn send(&mut self, data: Vec<u8>, channel: Channel) -> Result<(), TransportError> {
+        self.send_on_lane(CONTROL_LANE, &data, channel)
+    }
+
+    pub fn send_game(&mut self, data: Vec<u8>, channel: Channel, transfer_channel: u8) -> Result<(), TransportError> {
+        let lane_id = transfer_channel as usize + 1;
+        if lane_id > self.channel_count {
+            return Err(TransportError::InvalidChannel(transfer_channel));
+        }
 
-        std::mem::take(&mut self.pending_events)
+        self.send_on_lane(lane_id, &data, channel)
     }
 
-    pub fn send(&mut self, data: Vec<u8>, channel: Channel) -> Result<(), std::io::Error> {
-        let packet = match channel {
-            Channel::Reliable => {
-                let pkt = self.channel.encode(
-                    &data,
-                    PacketType::ReliableOrdered,
-                );
-                pkt
-            }
-            Channel::Unreliable => {
-                let pkt = self.channel.encode(
-                    &data,
-                    PacketType::Unreliable,
-                );
-                pkt
-            }
+    fn send_on_lane(&mut self, lane_id: usize, data: &[u8], channel: Channel) -> Result<(), TransportError> {
+        let max = max_unreliable_payload(channel);
+        if channel != Channel::Reliable && data.len() > max {
+            return Err(TransportError::PayloadTooLarge { size: data.len(), max });
+        }
+
+        let rto = self.stats.rto();
+        let lane = self.lane(lane_id);
+        let packets = match channel {
+            Channel::Reliable => lane.encode_reliable(data, MAX_RELIABLE_PAYLOAD, rto)?,
+            Channel::Unreliable => vec![lane.encode_unreliable(data, false)],
+            Channel::UnreliableOrdered => vec![lane.encode_unreliable(data, true)],
         };
 
-        self.try_send_packet(packet)?;
+        for pkt in packets {
+            self.try_send_packet(frame(lane_id, pkt))?;
+        }
+
+        Ok(())
+    }
+
+    fn lane(&mut self, lane_id: usize) -> &mut Lane {
+        while self.lanes.len() <= lane_id {
+            self.lanes.push(Lane::new());
+        }
+
+        &mut self.lanes[lane_id]
+    }
+
+    /// Reliable game data the relay never acknowledged. Only meaningful once
+    /// the connection is gone, as the lanes are drained in the process.
+    pub fn take_unacked_game_data(&mut self) -> Vec<UnackedMessage> {
+        self.lanes.iter_mut()
+            .enumerate()
+            .skip(CONTROL_LANE + 1)
+            .flat_map(|(lane_id, lane)| {
+                lane.take_unacked()
+                    .into_iter()
+                    .map(move |(last_seq, data)| UnackedMessage { lane_id, last_seq, data })
+            })
+            .collect()
+    }
 
+    fn send_datagram(&mut self, packet: &[u8]) -> Result<(), std::io::Error> {
+        self.socket.send_to(packet, self.server_addr)?;
+        self.stats.record_sent(packet.len());
         Ok(())
     }
 
     fn try_send_packet(&mut self, packet: Vec<u8>) -> Result<(), std::io::Error> {
-        match self.socket.send_to(&packet, self.server_addr) {
+        match self.send_datagram(&packet) {
             Ok(_) => Ok(()),
             Err(e) if e.kind() == ErrorKind::WouldBlock => {
                 self.pending_sends.push(packet);
@@ -123,8 +317,8 @@ impl ClientTransport {
     pub fn flush_pending_packets(&mut self) {
         let mut still_pending = Vec::new();
 
-        for packet in self.pending_sends.drain(..) {
-            match self.socket.send_to(&packet, self.server_addr) {
+        for packet in std::mem::take(&mut self.pending_sends) {
+            match self.send_datagram(&packet) {
                 Ok(_) => {}
                 Err(e) if e.kind() == ErrorKind::WouldBlock => {
                     still_pending.push(packet);
@@ -137,22 +331,130 @@ impl ClientTransport {
     }
 
     fn do_resends(&mut self) {
-        for packet in self.channel.collect_resends(Duration::from_millis(100)) {
-            self.try_send_packet(packet).unwrap();
+        if self.timed_out {
+            return;
+        }
+
+        let rto = self.stats.rto();
+        let now = Instant::now();
+        let mut resends = Vec::new();
+
+        for (lane_id, lane) in self.lanes.iter_mut().enumerate() {
+            match lane.collect_resends(rto, now) {
+                Ok(packets) => resends.extend(packets.into_iter().map(|packet| frame(lane_id, packet))),
+                Err(e) => {
+                    self.timed_out = true;
+                    self.connected = false;
+                    self.pending_events.push(ClientEvent::Disconnected { reason: e.to_string() });
+                    return;
+                }
+            }
+        }
+
+        for packet in resends {
+            self.stats.resends += 1;
+            let _ = self.try_send_packet(packet);
         }
     }
 
-    pub fn send_keepalive(&mut self) -> Result<(), std::io::Error> {
-        let payload = vec![3u8];
-        let pkt = self.channel.encode(
-            &payload,
-            PacketType::Unreliable,
-        );
-        self.socket.send_to(&pkt, self.server_addr)?;
+    fn handle_control(&mut self, payload: &[u8]) -> bool {
+        match payload {
+            [control::HANDSHAKE_ACK] => {
+                self.connected = true;
+                true
+            }
+            [control::PING, rest @ ..] => {
+                let mut pong = vec![control::PONG];
+                pong.extend_from_slice(rest);
+                self.send_control(&pong);
+                true
+            }
+            [control::PONG, rest @ ..] => {
+                self.handle_pong(rest);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_pong(&mut self, payload: &[u8]) {
+        if payload.len() < 12 {
+            return;
+        }
+
+        let seq = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+        let Some(index) = self.outstanding_pings.iter().position(|(s, _)| *s == seq) else {
+            return;
+        };
+        self.outstanding_pings.remove(index);
+
+        let mut sent_micros = [0u8; 8];
+        sent_micros.copy_from_slice(&payload[4..12]);
+        let sent_at = Duration::from_micros(u64::from_be_bytes(sent_micros));
+        let rtt = self.started.elapsed().saturating_sub(sent_at);
+
+        self.stats.record_rtt(rtt);
+    }
+
+    fn expire_pings(&mut self) {
+        while let Some((_, sent_at)) = self.outstanding_pings.front() {
+            if sent_at.elapsed() < PING_LOSS_TIMEOUT {
+                break;
+            }
+
+            self.outstanding_pings.pop_front();
+            self.stats.record_probe(true);
+        }
+    }
+
+    fn encode_control(&mut self, payload: &[u8]) -> Vec<u8> {
+        let pkt = self.lanes[CONTROL_LANE].encode_unreliable(payload, false);
+        frame(CONTROL_LANE, pkt)
+    }
+
+    fn send_control(&mut self, payload: &[u8]) {
+        let pkt = self.encode_control(payload);
+        let _ = self.try_send_packet(pkt);
+    }
+
+    fn send_handshake(&mut self) -> Result<(), std::io::Error> {
+        self.last_handshake = Instant::now();
+        let pkt = self.encode_control(&[control::HANDSHAKE]);
+        self.try_send_packet(pkt)
+    }
+
+    pub fn send_ping(&mut self) -> Result<(), std::io::Error> {
+        let seq = self.next_ping_seq;
+        self.next_ping_seq = self.next_ping_seq.wrapping_add(1);
+
+        let sent_micros = self.started.elapsed().as_micros() as u64;
+        let mut payload = vec![control::PING];
+        payload.extend(seq.to_be_bytes());
+        payload.extend(sent_micros.to_be_bytes());
+
+        let pkt = self.encode_control(&payload);
+        self.send_datagram(&pkt)?;
+        self.outstanding_pings.push_back((seq, Instant::now()));
+
         Ok(())
     }
 
     pub(crate) fn is_connected(&self) -> bool {
         self.connected
     }
+}
+
+/// The largest payload that fits a single unreliable packet on `channel`.
+pub fn max_unreliable_payload(channel: Channel) -> usize {
+    match channel {
+        Channel::UnreliableOrdered => MAX_UNRELIABLE_PAYLOAD - SEQUENCE_HEADER_SIZE,
+        _ => MAX_UNRELIABLE_PAYLOAD,
+    }
+}
+
+fn frame(lane_id: usize, packet: Vec<u8>) -> Vec<u8> {
+    let mut datagram = Vec::with_capacity(packet.len() + 1);
+    datagram.push(lane_id as u8);
+    datagram.extend(packet);
+    datagram
 }
\ No newline at end of file
@@ -2,4 +2,5 @@
 pub enum Channel {
     Reliable,
     Unreliable,
+    UnreliableOrdered,
 }
@@ -0,0 +1,8 @@
+pub const HANDSHAKE: u8 = 0xF0;
+pub const HANDSHAKE_ACK: u8 = 0xF1;
+pub const PING: u8 = 0xF2;
+pub const PONG: u8 = 0xF3;
+pub const FRAGMENT: u8 = 0xF4;
+pub const SEQUENCED: u8 = 0xF5;
+pub const RELIABLE: u8 = 0xF6;
+pub const ACK: u8 = 0xF7;
\ No newline at end of file
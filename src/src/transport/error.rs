@@ -7,4 +7,16 @@ pub enum TransportError {
 
     #[error("Clock may have gone backwards: {0}")]
     ClockError(#[from] std::time::SystemTimeError),
+
+    #[error("Failed to send packet: {0}")]
+    SendError(#[from] std::io::Error),
+
+    #[error("Payload of {size} bytes exceeds the {max} byte limit")]
+    PayloadTooLarge { size: usize, max: usize },
+
+    #[error("Invalid transfer channel: {0}")]
+    InvalidChannel(u8),
+
+    #[error("Reliable packet not acknowledged after {0} retries")]
+    RetriesExhausted(u32),
 }
\ No newline at end of file
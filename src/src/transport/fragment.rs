@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use crate::transport::control;
+
+// control byte + message id (u32) + fragment index (u16) + fragment count (u16)
+const FRAGMENT_HEADER_SIZE: usize = 9;
+
+pub fn split(message_id: u32, data: &[u8], max_payload: usize) -> Vec<Vec<u8>> {
+    let chunk_size = max_payload - FRAGMENT_HEADER_SIZE;
+    let count = data.len().div_ceil(chunk_size) as u16;
+
+    data.chunks(chunk_size)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let mut fragment = Vec::with_capacity(FRAGMENT_HEADER_SIZE + chunk.len());
+            fragment.push(control::FRAGMENT);
+            fragment.extend(message_id.to_be_bytes());
+            fragment.extend((index as u16).to_be_bytes());
+            fragment.extend(count.to_be_bytes());
+            fragment.extend_from_slice(chunk);
+            fragment
+        })
+        .collect()
+}
+
+pub fn max_message_size(max_payload: usize) -> usize {
+    (max_payload - FRAGMENT_HEADER_SIZE) * u16::MAX as usize
+}
+
+struct PartialMessage {
+    fragments: Vec<Option<Vec<u8>>>,
+    received: usize,
+}
+
+#[derive(Default)]
+pub struct Reassembler {
+    messages: HashMap<u32, PartialMessage>,
+}
+
+impl Reassembler {
+    /// Takes a fragment (including its control byte) and returns the full
+    /// message once every fragment of it has arrived.
+    pub fn push(&mut self, fragment: &[u8]) -> Option<Vec<u8>> {
+        if fragment.len() < FRAGMENT_HEADER_SIZE || fragment[0] != control::FRAGMENT {
+            return None;
+        }
+
+        let message_id = u32::from_be_bytes([fragment[1], fragment[2], fragment[3], fragment[4]]);
+        let index = u16::from_be_bytes([fragment[5], fragment[6]]) as usize;
+        let count = u16::from_be_bytes([fragment[7], fragment[8]]) as usize;
+
+        if count == 0 || index >= count {
+            return None;
+        }
+
+        let message = self.messages.entry(message_id).or_insert_with(|| PartialMessage {
+            fragments: vec![None; count],
+            received: 0,
+        });
+
+        if message.fragments.len() != count {
+            self.messages.remove(&message_id);
+            return None;
+        }
+
+        if message.fragments[index].is_none() {
+            message.fragments[index] = Some(fragment[FRAGMENT_HEADER_SIZE..].to_vec());
+            message.received += 1;
+        }
+
+        if message.received < count {
+            return None;
+        }
+
+        let message = self.messages.remove(&message_id)?;
+        Some(message.fragments.into_iter().flatten().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_PAYLOAD: usize = 32;
+    const CHUNK_SIZE: usize = MAX_PAYLOAD - FRAGMENT_HEADER_SIZE;
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    fn fragment(message_id: u32, index: u16, count: u16, chunk: &[u8]) -> Vec<u8> {
+        let mut fragment = vec![control::FRAGMENT];
+        fragment.extend(message_id.to_be_bytes());
+        fragment.extend(index.to_be_bytes());
+        fragment.extend(count.to_be_bytes());
+        fragment.extend_from_slice(chunk);
+        fragment
+    }
+
+    fn round_trip(len: usize) -> Option<Vec<u8>> {
+        let mut reassembler = Reassembler::default();
+        let fragments = split(7, &data(len), MAX_PAYLOAD);
+        assert!(fragments.iter().all(|f| f.len() <= MAX_PAYLOAD));
+
+        let mut result = None;
+        for fragment in &fragments {
+            assert!(result.is_none(), "message completed before its last fragment");
+            result = reassembler.push(fragment);
+        }
+        result
+    }
+
+    #[test]
+    fn round_trips_at_chunk_boundary() {
+        assert_eq!(split(0, &data(CHUNK_SIZE * 2), MAX_PAYLOAD).len(), 2);
+        assert_eq!(round_trip(CHUNK_SIZE * 2), Some(data(CHUNK_SIZE * 2)));
+
+        assert_eq!(split(0, &data(CHUNK_SIZE * 2 + 1), MAX_PAYLOAD).len(), 3);
+        assert_eq!(round_trip(CHUNK_SIZE * 2 + 1), Some(data(CHUNK_SIZE * 2 + 1)));
+    }
+
+    #[test]
+    fn round_trips_exactly_max_payload() {
+        assert_eq!(split(0, &data(MAX_PAYLOAD), MAX_PAYLOAD).len(), 2);
+        assert_eq!(round_trip(MAX_PAYLOAD), Some(data(MAX_PAYLOAD)));
+    }
+
+    #[test]
+    fn reassembles_out_of_order() {
+        let mut reassembler = Reassembler::default();
+        let fragments = split(1, &data(CHUNK_SIZE * 3), MAX_PAYLOAD);
+
+        assert_eq!(reassembler.push(&fragments[2]), None);
+        assert_eq!(reassembler.push(&fragments[0]), None);
+        assert_eq!(reassembler.push(&fragments[1]), Some(data(CHUNK_SIZE * 3)));
+    }
+
+    #[test]
+    fn ignores_duplicate_index() {
+        let mut reassembler = Reassembler::default();
+
+        assert_eq!(reassembler.push(&fragment(1, 0, 2, b"ab")), None);
+        assert_eq!(reassembler.push(&fragment(1, 0, 2, b"xx")), None);
+        assert_eq!(reassembler.push(&fragment(1, 1, 2, b"cd")), Some(b"abcd".to_vec()));
+    }
+
+    #[test]
+    fn rejects_out_of_range_index() {
+        let mut reassembler = Reassembler::default();
+
+        assert_eq!(reassembler.push(&fragment(1, 2, 2, b"ab")), None);
+        assert_eq!(reassembler.push(&fragment(1, 0, 0, b"ab")), None);
+        assert!(reassembler.messages.is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        let mut reassembler = Reassembler::default();
+
+        assert_eq!(reassembler.push(&fragment(1, 0, 1, b"")[..FRAGMENT_HEADER_SIZE - 1]), None);
+        assert_eq!(reassembler.push(&fragment(1, 0, 1, b"")), Some(vec![]));
+    }
+
+    #[test]
+    fn drops_message_on_mismatched_count() {
+        let mut reassembler = Reassembler::default();
+
+        assert_eq!(reassembler.push(&fragment(1, 0, 3, b"ab")), None);
+        assert_eq!(reassembler.push(&fragment(1, 1, 2, b"cd")), None);
+        assert!(reassembler.messages.is_empty());
+
+        assert_eq!(reassembler.push(&fragment(1, 1, 2, b"cd")), None);
+        assert_eq!(reassembler.push(&fragment(1, 0, 2, b"ab")), Some(b"abcd".to_vec()));
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::{Duration, Instant};
+use paperudp::packet::PacketType;
+use crate::transport::control;
+use crate::transport::error::TransportError;
+use crate::transport::fragment::{self, Reassembler};
+use crate::transport::reliable::{self, RELIABLE_HEADER_SIZE};
+
+// control byte + sequence number (u32)
+pub const SEQUENCE_HEADER_SIZE: usize = 5;
+
+/// One independent stream over the socket. Every lane has its own reliable
+/// ordering, fragment ids and unreliable sequence numbers, so a large
+/// transfer on one lane never holds up packets on another.
+pub struct Lane {
+    pub channel: paperudp::channel::Channel,
+    next_message_id: u32,
+    sender: reliable::Sender,
+    receiver: reliable::Receiver,
+    reassembler: Reassembler,
+    send_sequence: u32,
+    recv_sequence: Option<u32>,
+}
+
+impl Lane {
+    pub fn new() -> Self {
+        Self {
+            channel: paperudp::channel::Channel::new(),
+            next_message_id: 0,
+            sender: reliable::Sender::default(),
+            receiver: reliable::Receiver::default(),
+            reassembler: Reassembler::default(),
+            send_sequence: 0,
+            recv_sequence: None,
+        }
+    }
+
+    pub fn encode_reliable(&mut self, data: &[u8], max_payload: usize, rto: Duration) -> Result<Vec<Vec<u8>>, TransportError> {
+        let max_piece = max_payload - RELIABLE_HEADER_SIZE;
+
+        let pieces = if data.len() <= max_piece {
+            vec![data.to_vec()]
+        } else {
+            let max = fragment::max_message_size(max_piece);
+            if data.len() > max {
+                return Err(TransportError::PayloadTooLarge { size: data.len(), max });
+            }
+
+            let message_id = self.next_message_id;
+            self.next_message_id = self.next_message_id.wrapping_add(1);
+            fragment::split(message_id, data, max_piece)
+        };
+
+        Ok(self.sender.push(pieces, rto, Instant::now())
+            .iter()
+            .map(|packet| self.channel.encode(packet, PacketType::Unreliable))
+            .collect())
+    }
+
+    pub fn collect_resends(&mut self, rto: Duration, now: Instant) -> Result<Vec<Vec<u8>>, TransportError> {
+        Ok(self.sender.collect_resends(rto, now)?
+            .iter()
+            .map(|packet| self.channel.encode(packet, PacketType::Unreliable))
+            .collect())
+    }
+
+    pub fn has_unacked(&self) -> bool {
+        self.sender.has_unacked()
+    }
+
+    /// Reliable messages the peer has not acknowledged, each with the
+    /// sequence number of its last packet. Drains the lane.
+    pub fn take_unacked(&mut self) -> Vec<(u32, Vec<u8>)> {
+        self.sender.take_unacked()
+    }
+
+    pub fn receive_ack(&mut self, payload: &[u8]) {
+        self.sender.ack(payload);
+    }
+
+    /// Takes a reliable packet and returns the messages it completes, along
+    /// with the ack to send back.
+    pub fn receive_reliable(&mut self, payload: &[u8]) -> (Vec<Vec<u8>>, Vec<u8>) {
+        let messages = self.receiver.push(payload)
+            .into_iter()
+            .filter_map(|piece| self.reassemble(piece))
+            .collect();
+        let ack = self.channel.encode(&self.receiver.ack(), PacketType::Unreliable);
+
+        (messages, ack)
+    }
+
+    pub fn encode_unreliable(&mut self, data: &[u8], sequenced: bool) -> Vec<u8> {
+        if !sequenced {
+            return self.channel.encode(data, PacketType::Unreliable);
+        }
+
+        let seq = self.send_sequence;
+        self.send_sequence = self.send_sequence.wrapping_add(1);
+
+        let mut payload = Vec::with_capacity(SEQUENCE_HEADER_SIZE + data.len());
+        payload.push(control::SEQUENCED);
+        payload.extend(seq.to_be_bytes());
+        payload.extend_from_slice(data);
+
+        self.channel.encode(&payload, PacketType::Unreliable)
+    }
+
+    fn reassemble(&mut self, payload: Vec<u8>) -> Option<Vec<u8>> {
+        if payload.first() == Some(&control::FRAGMENT) {
+            self.reassembler.push(&payload)
+        } else {
+            Some(payload)
+        }
+    }
+
+    /// Strips the sequence header, dropping packets older than the newest one
+    /// already delivered on this lane.
+    pub fn accept_sequenced(&mut self, payload: &[u8]) -> Option<Vec<u8>> {
+        if payload.len() < SEQUENCE_HEADER_SIZE {
+            return None;
+        }
+
+        let seq = u32::from_be_bytes([payload[1], payload[2], payload[3], payload[4]]);
+        if let Some(last) = self.recv_sequence
+            && (seq.wrapping_sub(last) as i32) <= 0
+        {
+            return None;
+        }
+
+        self.recv_sequence = Some(seq);
+        Some(payload[SEQUENCE_HEADER_SIZE..].to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequenced(seq: u32, data: &[u8]) -> Vec<u8> {
+        let mut payload = vec![control::SEQUENCED];
+        payload.extend(seq.to_be_bytes());
+        payload.extend_from_slice(data);
+        payload
+    }
+
+    #[test]
+    fn accepts_newer_sequence_numbers() {
+        let mut lane = Lane::new();
+
+        assert_eq!(lane.accept_sequenced(&sequenced(5, b"a")), Some(b"a".to_vec()));
+        assert_eq!(lane.accept_sequenced(&sequenced(6, b"b")), Some(b"b".to_vec()));
+        assert_eq!(lane.accept_sequenced(&sequenced(10, b"c")), Some(b"c".to_vec()));
+    }
+
+    #[test]
+    fn drops_stale_sequence_numbers() {
+        let mut lane = Lane::new();
+
+        assert!(lane.accept_sequenced(&sequenced(10, b"a")).is_some());
+        assert_eq!(lane.accept_sequenced(&sequenced(10, b"dup")), None);
+        assert_eq!(lane.accept_sequenced(&sequenced(9, b"old")), None);
+        assert_eq!(lane.accept_sequenced(&sequenced(0, b"older")), None);
+        assert_eq!(lane.accept_sequenced(&sequenced(11, b"b")), Some(b"b".to_vec()));
+    }
+
+    #[test]
+    fn accepts_sequence_wrap_around() {
+        let mut lane = Lane::new();
+
+        assert!(lane.accept_sequenced(&sequenced(u32::MAX - 1, b"a")).is_some());
+        assert!(lane.accept_sequenced(&sequenced(u32::MAX, b"b")).is_some());
+        assert_eq!(lane.accept_sequenced(&sequenced(0, b"c")), Some(b"c".to_vec()));
+        assert_eq!(lane.accept_sequenced(&sequenced(u32::MAX, b"late")), None);
+        assert_eq!(lane.accept_sequenced(&sequenced(1, b"d")), Some(b"d".to_vec()));
+    }
+
+    #[test]
+    fn drops_truncated_header() {
+        let mut lane = Lane::new();
+
+        assert_eq!(lane.accept_sequenced(&sequenced(0, b"")[..SEQUENCE_HEADER_SIZE - 1]), None);
+        assert_eq!(lane.accept_sequenced(&sequenced(0, b"")), Some(vec![]));
+    }
+}
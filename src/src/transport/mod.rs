@@ -1,3 +1,8 @@
 pub mod error;
 pub mod common;
 pub mod client;
+pub mod stats;
+mod control;
+mod fragment;
+mod lane;
+mod reliable;
\ No newline at end of file
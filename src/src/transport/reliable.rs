@@ -0,0 +1,312 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use crate::transport::control;
+use crate::transport::error::TransportError;
+use crate::transport::fragment::Reassembler;
+
+// control byte + sequence number (u32)
+pub const RELIABLE_HEADER_SIZE: usize = 5;
+const MAX_RESEND_BACKOFF: Duration = Duration::from_secs(8);
+const MAX_RESEND_RETRIES: u32 = 10;
+const RECEIVE_WINDOW: u32 = 1024;
+
+struct InFlight {
+    seq: u32,
+    message_end: u32,
+    packet: Vec<u8>,
+    retries: u32,
+    next_send: Instant,
+}
+
+/// Numbers outgoing reliable packets and resends them with exponential
+/// backoff until the peer acknowledges them. Acks are cumulative: an ack
+/// carries the first sequence number the peer is still missing.
+#[derive(Default)]
+pub struct Sender {
+    next_seq: u32,
+    acked: u32,
+    in_flight: VecDeque<InFlight>,
+}
+
+impl Sender {
+    /// Queues the pieces of one message and returns them framed for sending.
+    pub fn push(&mut self, pieces: Vec<Vec<u8>>, rto: Duration, now: Instant) -> Vec<Vec<u8>> {
+        let message_end = self.next_seq.wrapping_add(pieces.len() as u32).wrapping_sub(1);
+
+        pieces.into_iter()
+            .map(|piece| {
+                let seq = self.next_seq;
+                self.next_seq = self.next_seq.wrapping_add(1);
+
+                let mut packet = Vec::with_capacity(RELIABLE_HEADER_SIZE + piece.len());
+                packet.push(control::RELIABLE);
+                packet.extend(seq.to_be_bytes());
+                packet.extend(piece);
+
+                self.in_flight.push_back(InFlight {
+                    seq,
+                    message_end,
+                    packet: packet.clone(),
+                    retries: 0,
+                    next_send: now + rto,
+                });
+                packet
+            })
+            .collect()
+    }
+
+    /// Takes an ack packet (including its control byte).
+    pub fn ack(&mut self, packet: &[u8]) {
+        let [control::ACK, a, b, c, d] = *packet else {
+            return;
+        };
+
+        let next = u32::from_be_bytes([a, b, c, d]);
+        if seq_before(next, self.acked) || seq_before(self.next_seq, next) {
+            return;
+        }
+        self.acked = next;
+
+        // Pieces of a fragmented message stay until the whole message is
+        // acknowledged, so take_unacked can still rebuild it.
+        while self.in_flight.front().is_some_and(|f| seq_before(f.message_end, next)) {
+            self.in_flight.pop_front();
+        }
+    }
+
+    pub fn has_unacked(&self) -> bool {
+        !self.in_flight.is_empty()
+    }
+
+    pub fn collect_resends(&mut self, rto: Duration, now: Instant) -> Result<Vec<Vec<u8>>, TransportError> {
+        let mut resends = Vec::new();
+
+        for entry in self.in_flight.iter_mut() {
+            if seq_before(entry.seq, self.acked) || now < entry.next_send {
+                continue;
+            }
+
+            if entry.retries >= MAX_RESEND_RETRIES {
+                return Err(TransportError::RetriesExhausted(MAX_RESEND_RETRIES));
+            }
+
+            entry.retries += 1;
+            entry.next_send = now + rto.saturating_mul(1 << entry.retries.min(16)).min(MAX_RESEND_BACKOFF);
+            resends.push(entry.packet.clone());
+        }
+
+        Ok(resends)
+    }
+
+    /// Every message not yet fully acknowledged, with the sequence number
+    /// of its last piece, in the order they were sent.
+    pub fn take_unacked(&mut self) -> Vec<(u32, Vec<u8>)> {
+        let mut reassembler = Reassembler::default();
+        let mut messages = Vec::new();
+
+        for entry in std::mem::take(&mut self.in_flight) {
+            let piece = &entry.packet[RELIABLE_HEADER_SIZE..];
+            let message = if piece.first() == Some(&control::FRAGMENT) {
+                reassembler.push(piece)
+            } else {
+                Some(piece.to_vec())
+            };
+
+            if let Some(message) = message {
+                messages.push((entry.message_end, message));
+            }
+        }
+
+        messages
+    }
+}
+
+/// Delivers reliable packets in order, holding back any that arrive ahead
+/// of a gap until it is filled.
+#[derive(Default)]
+pub struct Receiver {
+    next_seq: u32,
+    out_of_order: HashMap<u32, Vec<u8>>,
+}
+
+impl Receiver {
+    /// Takes a reliable packet (including its control byte) and returns the
+    /// payloads that are now in order.
+    pub fn push(&mut self, packet: &[u8]) -> Vec<Vec<u8>> {
+        if packet.len() < RELIABLE_HEADER_SIZE {
+            return vec![];
+        }
+
+        let seq = u32::from_be_bytes([packet[1], packet[2], packet[3], packet[4]]);
+        if seq.wrapping_sub(self.next_seq) >= RECEIVE_WINDOW {
+            return vec![];
+        }
+
+        self.out_of_order.entry(seq).or_insert_with(|| packet[RELIABLE_HEADER_SIZE..].to_vec());
+
+        let mut delivered = Vec::new();
+        while let Some(payload) = self.out_of_order.remove(&self.next_seq) {
+            delivered.push(payload);
+            self.next_seq = self.next_seq.wrapping_add(1);
+        }
+        delivered
+    }
+
+    pub fn ack(&self) -> Vec<u8> {
+        let mut ack = vec![control::ACK];
+        ack.extend(self.next_seq.to_be_bytes());
+        ack
+    }
+}
+
+pub fn seq_before(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::fragment;
+
+    const RTO: Duration = Duration::from_millis(100);
+
+    fn ack(next: u32) -> Vec<u8> {
+        let mut ack = vec![control::ACK];
+        ack.extend(next.to_be_bytes());
+        ack
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let start = Instant::now();
+        let mut sender = Sender::default();
+        sender.push(vec![b"a".to_vec()], RTO, start);
+
+        let mut due = start + RTO;
+        for retry in 1..=4 {
+            assert!(sender.collect_resends(RTO, due - Duration::from_millis(1)).unwrap().is_empty());
+            assert_eq!(sender.collect_resends(RTO, due).unwrap().len(), 1, "resend {}", retry);
+            due += RTO * (1 << retry);
+        }
+    }
+
+    #[test]
+    fn caps_backoff() {
+        let mut now = Instant::now();
+        let mut sender = Sender::default();
+        sender.push(vec![b"a".to_vec()], RTO, now);
+
+        for _ in 0..MAX_RESEND_RETRIES {
+            now += MAX_RESEND_BACKOFF;
+            assert_eq!(sender.collect_resends(RTO, now).unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn gives_up_after_max_retries() {
+        let mut now = Instant::now();
+        let mut sender = Sender::default();
+        sender.push(vec![b"a".to_vec()], RTO, now);
+
+        for _ in 0..MAX_RESEND_RETRIES {
+            now += MAX_RESEND_BACKOFF;
+            sender.collect_resends(RTO, now).unwrap();
+        }
+
+        now += MAX_RESEND_BACKOFF;
+        assert!(matches!(
+            sender.collect_resends(RTO, now),
+            Err(TransportError::RetriesExhausted(MAX_RESEND_RETRIES))
+        ));
+    }
+
+    #[test]
+    fn stops_resending_once_acked() {
+        let now = Instant::now();
+        let mut sender = Sender::default();
+        sender.push(vec![b"a".to_vec()], RTO, now);
+        sender.push(vec![b"b".to_vec()], RTO, now);
+
+        sender.ack(&ack(1));
+        let resends = sender.collect_resends(RTO, now + RTO).unwrap();
+        assert_eq!(resends.len(), 1);
+        assert!(resends[0].ends_with(b"b"));
+
+        sender.ack(&ack(2));
+        assert!(!sender.has_unacked());
+    }
+
+    #[test]
+    fn tells_identical_payloads_apart() {
+        let now = Instant::now();
+        let mut sender = Sender::default();
+        sender.push(vec![b"rpc".to_vec()], RTO, now);
+        sender.push(vec![b"rpc".to_vec()], RTO, now + RTO);
+
+        let resends = sender.collect_resends(RTO, now + RTO).unwrap();
+        assert_eq!(resends.len(), 1);
+        assert_eq!(resends[0][1..RELIABLE_HEADER_SIZE], 0u32.to_be_bytes());
+
+        sender.ack(&ack(1));
+        let resends = sender.collect_resends(RTO, now + RTO * 2).unwrap();
+        assert_eq!(resends.len(), 1);
+        assert_eq!(resends[0][1..RELIABLE_HEADER_SIZE], 1u32.to_be_bytes());
+    }
+
+    #[test]
+    fn ignores_stale_and_impossible_acks() {
+        let now = Instant::now();
+        let mut sender = Sender::default();
+        sender.push(vec![b"a".to_vec(), b"b".to_vec()], RTO, now);
+
+        sender.ack(&ack(5));
+        assert_eq!(sender.collect_resends(RTO, now + RTO).unwrap().len(), 2);
+
+        sender.ack(&ack(1));
+        sender.ack(&ack(0));
+        assert_eq!(sender.collect_resends(RTO, now + RTO * 3).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn keeps_fragmented_message_until_fully_acked() {
+        let now = Instant::now();
+        let data: Vec<u8> = (0..40).collect();
+        let mut sender = Sender::default();
+        sender.push(fragment::split(0, &data, 20), RTO, now);
+        sender.push(vec![b"next".to_vec()], RTO, now);
+
+        // Pieces 0 and 1 arrived, so only 2, 3 and the next message go again.
+        sender.ack(&ack(2));
+        assert_eq!(sender.collect_resends(RTO, now + RTO).unwrap().len(), 3);
+
+        let unacked = sender.take_unacked();
+        assert_eq!(unacked, vec![(3, data), (4, b"next".to_vec())]);
+    }
+
+    #[test]
+    fn receives_in_order_across_gaps() {
+        let mut sender = Sender::default();
+        let packets = sender.push(vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()], RTO, Instant::now());
+
+        let mut receiver = Receiver::default();
+        assert!(receiver.push(&packets[1]).is_empty());
+        assert_eq!(receiver.ack(), ack(0));
+        assert_eq!(receiver.push(&packets[0]), vec![b"a".to_vec(), b"b".to_vec()]);
+        assert!(receiver.push(&packets[0]).is_empty());
+        assert_eq!(receiver.push(&packets[2]), vec![b"c".to_vec()]);
+        assert_eq!(receiver.ack(), ack(3));
+    }
+
+    #[test]
+    fn receives_across_sequence_wrap_around() {
+        let mut sender = Sender { next_seq: u32::MAX, acked: u32::MAX, ..Default::default() };
+        let packets = sender.push(vec![b"a".to_vec(), b"b".to_vec()], RTO, Instant::now());
+
+        let mut receiver = Receiver { next_seq: u32::MAX, ..Default::default() };
+        assert_eq!(receiver.push(&packets[0]), vec![b"a".to_vec()]);
+        assert_eq!(receiver.push(&packets[1]), vec![b"b".to_vec()]);
+
+        sender.ack(&receiver.ack());
+        assert!(!sender.has_unacked());
+    }
+}
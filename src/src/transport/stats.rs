@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+const RTT_ALPHA: f64 = 1.0 / 8.0;
+const RTT_BETA: f64 = 1.0 / 4.0;
+const JITTER_GAIN: f64 = 1.0 / 16.0;
+const LOSS_GAIN: f64 = 1.0 / 10.0;
+const INITIAL_RTO: Duration = Duration::from_millis(200);
+pub const MIN_RTO: Duration = Duration::from_millis(30);
+pub const MAX_RTO: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NetworkStats {
+    pub rtt: Duration,
+    pub rtt_var: Duration,
+    pub last_rtt: Duration,
+    pub jitter: Duration,
+    pub packet_loss: f64,
+    pub resends: u64,
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+impl NetworkStats {
+    pub fn has_rtt_sample(&self) -> bool {
+        !self.last_rtt.is_zero()
+    }
+
+    /// Retransmission timeout in the style of Jacobson/Karels (RFC 6298):
+    /// smoothed RTT plus four times its variance.
+    pub fn rto(&self) -> Duration {
+        if !self.has_rtt_sample() {
+            return INITIAL_RTO;
+        }
+
+        (self.rtt + self.rtt_var * 4).clamp(MIN_RTO, MAX_RTO)
+    }
+
+    pub fn record_rtt(&mut self, sample: Duration) {
+        let sample_secs = sample.as_secs_f64();
+
+        if !self.has_rtt_sample() {
+            self.rtt = sample;
+            self.rtt_var = sample / 2;
+        } else {
+            let srtt = self.rtt.as_secs_f64();
+            let var = (1.0 - RTT_BETA) * self.rtt_var.as_secs_f64()
+                + RTT_BETA * (srtt - sample_secs).abs();
+            self.rtt_var = Duration::from_secs_f64(var);
+            self.rtt = Duration::from_secs_f64((1.0 - RTT_ALPHA) * srtt + RTT_ALPHA * sample_secs);
+
+            // RFC 3550 style interarrival jitter between consecutive samples.
+            let delta = (sample_secs - self.last_rtt.as_secs_f64()).abs();
+            let jitter = self.jitter.as_secs_f64();
+            self.jitter = Duration::from_secs_f64(jitter + (delta - jitter) * JITTER_GAIN);
+        }
+
+        self.last_rtt = sample;
+        self.record_probe(false);
+    }
+
+    pub fn record_probe(&mut self, lost: bool) {
+        let outcome = if lost { 1.0 } else { 0.0 };
+        self.packet_loss += (outcome - self.packet_loss) * LOSS_GAIN;
+    }
+
+    pub fn record_sent(&mut self, bytes: usize) {
+        self.packets_sent += 1;
+        self.bytes_sent += bytes as u64;
+    }
+
+    pub fn record_received(&mut self, bytes: usize) {
+        self.packets_received += 1;
+        self.bytes_received += bytes as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rto_starts_at_initial_value() {
+        assert_eq!(NetworkStats::default().rto(), INITIAL_RTO);
+    }
+
+    #[test]
+    fn rto_follows_first_sample() {
+        let mut stats = NetworkStats::default();
+        stats.record_rtt(Duration::from_millis(100));
+
+        // srtt 100ms + 4 * rttvar 50ms
+        assert_eq!(stats.rto(), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn rto_tracks_variance() {
+        let mut stats = NetworkStats::default();
+        stats.record_rtt(Duration::from_millis(100));
+        stats.record_rtt(Duration::from_millis(100));
+        let steady = stats.rto();
+
+        stats.record_rtt(Duration::from_millis(400));
+        assert!(stats.rto() > steady);
+    }
+
+    #[test]
+    fn rto_is_clamped() {
+        let mut stats = NetworkStats::default();
+        stats.record_rtt(Duration::from_millis(1));
+        assert_eq!(stats.rto(), MIN_RTO);
+
+        let mut stats = NetworkStats::default();
+        stats.record_rtt(Duration::from_secs(1));
+        assert_eq!(stats.rto(), MAX_RTO);
+    }
+}